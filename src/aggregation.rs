@@ -0,0 +1,191 @@
+use crate::queue::SignalQueue;
+use crate::types::{Outcome, Trade, TradeSide};
+use async_channel::Receiver;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+type Key = (String, String, Outcome, TradeSide);  // Wallet (lowercase), market, outcome, side
+
+#[derive(Debug)]
+struct Pending {
+    trade: Trade,
+    notional: f64,
+    fills: usize,
+    deadline: Instant,
+}
+
+// Coalesces a whale order that reaches us as many small fills into one
+// signal. The window opens on the first fill and isn't extended by later
+// ones, so a steady trickle can't hold a signal back indefinitely.
+#[derive(Debug)]
+pub struct FillAggregator {
+    window: Duration,
+    pending: HashMap<Key, Pending>,
+}
+
+impl FillAggregator {
+    pub fn new(window: Duration) -> Self {
+        Self { window, pending: HashMap::new() }
+    }
+
+    // Returns the fill straight back when aggregation is off
+    pub fn push(&mut self, now: Instant, fill: Trade) -> Option<Trade> {
+        if self.window.is_zero() {
+            return Some(fill);
+        }
+
+        let key = (fill.wallet.to_lowercase(), fill.market_id.clone(), fill.outcome, fill.side.clone());
+        match self.pending.get_mut(&key) {
+            Some(pending) => {
+                pending.notional += fill.shares * fill.price;
+                pending.trade.shares += fill.shares;
+                pending.trade.price = pending.notional / pending.trade.shares;
+                // Keep the first fill's timestamp so freshness is judged on the oldest part
+                pending.trade.timestamp = pending.trade.timestamp.min(fill.timestamp);
+                if pending.trade.token_id.is_none() {
+                    pending.trade.token_id = fill.token_id;
+                }
+                pending.fills += 1;
+            }
+            None => {
+                self.pending.insert(key, Pending {
+                    notional: fill.shares * fill.price,
+                    trade: fill,
+                    fills: 1,
+                    deadline: now + self.window,
+                });
+            }
+        }
+        None
+    }
+
+    // Signals whose window has closed, oldest first
+    pub fn due(&mut self, now: Instant) -> Vec<Trade> {
+        let keys: Vec<Key> = self.pending.iter()
+            .filter(|(_, p)| p.deadline <= now)
+            .map(|(k, _)| k.clone())
+            .collect();
+        let mut ready: Vec<Pending> = keys.iter().filter_map(|k| self.pending.remove(k)).collect();
+        ready.sort_by_key(|p| p.deadline);
+        ready.into_iter().map(Self::finish).collect()
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending.values().map(|p| p.deadline).min()
+    }
+
+    // Everything pending, regardless of window
+    pub fn drain(&mut self) -> Vec<Trade> {
+        let mut pending: Vec<Pending> = self.pending.drain().map(|(_, p)| p).collect();
+        pending.sort_by_key(|p| p.deadline);
+        pending.into_iter().map(Self::finish).collect()
+    }
+
+    fn finish(pending: Pending) -> Trade {
+        if pending.fills > 1 {
+            tracing::info!("Coalesced {} fills from {} on {}: {:.2} shares @ ${:.4}",
+                pending.fills, pending.trade.wallet, pending.trade.market_id,
+                pending.trade.shares, pending.trade.price);
+        }
+        pending.trade
+    }
+
+    // Sits between the watcher and the pipeline's queue
+    pub fn start(mut self, input: Receiver<Trade>, output: SignalQueue) {
+        tokio::spawn(async move {
+            loop {
+                let deadline = self.next_deadline();
+                tokio::select! {
+                    fill = input.recv() => match fill {
+                        Ok(fill) => {
+                            if let Some(trade) = self.push(Instant::now(), fill) {
+                                output.push(trade).await;
+                            }
+                        }
+                        Err(_) => {
+                            for trade in self.drain() {
+                                output.push(trade).await;
+                            }
+                            output.close();
+                            return;
+                        }
+                    },
+                    _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now).into()), if deadline.is_some() => {}
+                }
+
+                for trade in self.due(Instant::now()) {
+                    output.push(trade).await;
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::fixtures;
+    use crate::types::OverflowPolicy;
+    use std::sync::Arc;
+
+    fn fill(wallet: &str, side: TradeSide, shares: f64, price: f64, timestamp: i64) -> Trade {
+        Trade {
+            wallet: wallet.to_string(),
+            side,
+            shares,
+            price,
+            timestamp,
+            ..fixtures::trade("market1")
+        }
+    }
+
+    #[test]
+    fn test_fills_coalesce_at_average_price() {
+        let mut agg = FillAggregator::new(Duration::from_millis(500));
+        let t0 = Instant::now();
+
+        assert!(agg.push(t0, fill("0xwhale", TradeSide::BUY, 100.0, 0.50, 10)).is_none());
+        agg.push(t0 + Duration::from_millis(100), fill("0xWHALE", TradeSide::BUY, 300.0, 0.54, 11));
+        // Selling is a different signal
+        agg.push(t0 + Duration::from_millis(200), fill("0xwhale", TradeSide::SELL, 50.0, 0.55, 11));
+        assert!(agg.due(t0 + Duration::from_millis(400)).is_empty());
+
+        let signals = agg.due(t0 + Duration::from_millis(500));
+        assert_eq!(signals.len(), 1);
+        assert_eq!(signals[0].shares, 400.0);
+        assert!((signals[0].price - 0.53).abs() < 1e-9);
+        assert_eq!(signals[0].timestamp, 10);
+
+        assert_eq!(agg.next_deadline(), Some(t0 + Duration::from_millis(700)));
+        assert_eq!(agg.drain().len(), 1);
+        assert!(agg.next_deadline().is_none());
+    }
+
+    #[test]
+    fn test_zero_window_passes_through() {
+        let mut agg = FillAggregator::new(Duration::ZERO);
+        let passed = agg.push(Instant::now(), fill("0xwhale", TradeSide::BUY, 100.0, 0.5, 10));
+        assert_eq!(passed.unwrap().shares, 100.0);
+        assert!(agg.next_deadline().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_start_forwards_one_signal_per_burst() {
+        let (tx, rx) = async_channel::bounded(10);
+        let out = SignalQueue::new(10, OverflowPolicy::Block, Duration::from_secs(30), Arc::new(|_: &Trade| 0.0));
+        FillAggregator::new(Duration::from_millis(20)).start(rx, out.clone());
+
+        for _ in 0..5 {
+            tx.send(fill("0xwhale", TradeSide::BUY, 10.0, 0.5, 10)).await.unwrap();
+        }
+        let signal = out.recv().await.unwrap();
+        assert_eq!(signal.shares, 50.0);
+        out.release(&signal.market_id);
+
+        // Whatever is pending goes out when the watcher shuts down
+        tx.send(fill("0xother", TradeSide::BUY, 10.0, 0.5, 11)).await.unwrap();
+        drop(tx);
+        assert_eq!(out.recv().await.unwrap().wallet, "0xother");
+        assert!(out.recv().await.is_none());
+    }
+}
@@ -0,0 +1,326 @@
+use crate::api::MarketHistory;
+use crate::types::{Market, Outcome, Trade, TradeSide};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+const UNCATEGORIZED: &str = "uncategorized";
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CategoryStats {
+    pub category: String,
+    pub positions: usize,
+    pub win_rate: f64,
+    pub roi: f64,
+}
+
+// A wallet's track record, from positions closed inside the lookback window
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WhaleStats {
+    pub wallet: String,
+    pub trades: usize,
+    pub closed_positions: usize,
+    pub open_positions: usize,
+    pub win_rate: f64,
+    pub roi: f64,  // Realized PnL over the cost of the closed positions
+    pub realized_pnl: f64,
+    pub invested: f64,
+    pub avg_holding_secs: f64,
+    pub consistency: f64,  // Mean over std dev of per-position returns
+    pub categories: Vec<CategoryStats>,  // Best ROI first
+}
+
+impl WhaleStats {
+    // Size multiplier for copies of this wallet. Neutral until there's
+    // enough history to judge it.
+    pub fn confidence(&self, min_positions: usize) -> f64 {
+        if self.closed_positions < min_positions {
+            return 1.0;
+        }
+        (1.0 + self.roi).clamp(0.5, 1.5)
+    }
+
+    // Why this wallet should stop being copied, if it should
+    pub fn underperformance(&self, min_positions: usize, min_roi: f64) -> Option<String> {
+        if self.closed_positions < min_positions || self.roi >= min_roi {
+            return None;
+        }
+        Some(format!("ROI {:.1}% over {} positions is below {:.1}%",
+            self.roi * 100.0, self.closed_positions, min_roi * 100.0))
+    }
+}
+
+#[derive(Debug, Default)]
+struct OpenPosition {
+    shares: f64,
+    cost: f64,  // Of the shares still held
+    closed_cost: f64,  // Of the shares sold so far
+    proceeds: f64,
+    opened_at: i64,
+}
+
+#[derive(Debug)]
+struct ClosedPosition {
+    category: String,
+    cost: f64,
+    proceeds: f64,
+    held_secs: i64,
+}
+
+impl ClosedPosition {
+    fn pnl(&self) -> f64 {
+        self.proceeds - self.cost
+    }
+}
+
+// Rebuilds the wallet's positions per market outcome at average cost. A
+// position closes when it's sold out or its market resolves; sells of shares
+// bought before the window have no known cost and are ignored.
+pub fn score(wallet: &str, trades: &[Trade], markets: &HashMap<String, Market>) -> WhaleStats {
+    let mut trades: Vec<&Trade> = trades.iter().collect();
+    trades.sort_by_key(|t| t.timestamp);
+
+    let category = |market_id: &str| markets.get(market_id)
+        .and_then(|m| m.category.clone())
+        .unwrap_or_else(|| UNCATEGORIZED.to_string());
+
+    let mut open: HashMap<(String, Outcome), OpenPosition> = HashMap::new();
+    let mut closed = Vec::new();
+
+    for trade in &trades {
+        let key = (trade.market_id.clone(), trade.outcome);
+        match trade.side {
+            TradeSide::BUY => {
+                let position = open.entry(key).or_insert_with(|| OpenPosition {
+                    opened_at: trade.timestamp,
+                    ..Default::default()
+                });
+                position.shares += trade.shares;
+                position.cost += trade.shares * trade.price;
+            }
+            TradeSide::SELL => {
+                let Some(position) = open.get_mut(&key) else {
+                    continue;
+                };
+                let sold = trade.shares.min(position.shares);
+                let cost = position.cost * sold / position.shares;
+                position.shares -= sold;
+                position.cost -= cost;
+                position.closed_cost += cost;
+                position.proceeds += sold * trade.price;
+
+                if position.shares <= 1e-9 {
+                    let position = open.remove(&key).unwrap();
+                    closed.push(ClosedPosition {
+                        category: category(&trade.market_id),
+                        cost: position.closed_cost,
+                        proceeds: position.proceeds,
+                        held_secs: trade.timestamp - position.opened_at,
+                    });
+                }
+            }
+        }
+    }
+
+    // Whatever is still held pays out $1 or nothing if the market has resolved
+    let mut open_positions = 0;
+    for ((market_id, outcome), position) in open {
+        let resolved = markets.get(&market_id).and_then(|m| m.resolved.map(|r| (r, m.end_date)));
+        let Some((winner, end_date)) = resolved else {
+            open_positions += 1;
+            continue;
+        };
+        let payout = if winner == outcome { position.shares } else { 0.0 };
+        let closed_at = end_date.unwrap_or(position.opened_at).max(position.opened_at);
+        closed.push(ClosedPosition {
+            category: category(&market_id),
+            cost: position.closed_cost + position.cost,
+            proceeds: position.proceeds + payout,
+            held_secs: closed_at - position.opened_at,
+        });
+    }
+
+    let mut stats = summarize(&closed);
+    stats.wallet = wallet.to_string();
+    stats.trades = trades.len();
+    stats.open_positions = open_positions;
+
+    let mut by_category: BTreeMap<&str, Vec<&ClosedPosition>> = BTreeMap::new();
+    for position in &closed {
+        by_category.entry(&position.category).or_default().push(position);
+    }
+    stats.categories = by_category.into_iter()
+        .map(|(category, positions)| {
+            let cost: f64 = positions.iter().map(|p| p.cost).sum();
+            let pnl: f64 = positions.iter().map(|p| p.pnl()).sum();
+            CategoryStats {
+                category: category.to_string(),
+                positions: positions.len(),
+                win_rate: positions.iter().filter(|p| p.pnl() > 0.0).count() as f64 / positions.len() as f64,
+                roi: if cost > 0.0 { pnl / cost } else { 0.0 },
+            }
+        })
+        .collect();
+    stats.categories.sort_by(|a, b| b.roi.total_cmp(&a.roi));
+
+    stats
+}
+
+fn summarize(closed: &[ClosedPosition]) -> WhaleStats {
+    let n = closed.len();
+    if n == 0 {
+        return WhaleStats::default();
+    }
+
+    let invested: f64 = closed.iter().map(|p| p.cost).sum();
+    let realized_pnl: f64 = closed.iter().map(|p| p.pnl()).sum();
+    let returns: Vec<f64> = closed.iter()
+        .filter(|p| p.cost > 0.0)
+        .map(|p| p.pnl() / p.cost)
+        .collect();
+
+    let consistency = if returns.len() >= 2 {
+        let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+        let var = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+        if var > 0.0 { mean / var.sqrt() } else { 0.0 }
+    } else {
+        0.0
+    };
+
+    WhaleStats {
+        closed_positions: n,
+        win_rate: closed.iter().filter(|p| p.pnl() > 0.0).count() as f64 / n as f64,
+        roi: if invested > 0.0 { realized_pnl / invested } else { 0.0 },
+        realized_pnl,
+        invested,
+        avg_holding_secs: closed.iter().map(|p| p.held_secs as f64).sum::<f64>() / n as f64,
+        consistency,
+        ..Default::default()
+    }
+}
+
+// Pulls history for wallets and scores them, fetching each market once
+pub struct WhaleScorer<'a> {
+    source: &'a dyn MarketHistory,
+    markets: HashMap<String, Market>,
+}
+
+impl<'a> WhaleScorer<'a> {
+    pub fn new(source: &'a dyn MarketHistory) -> Self {
+        Self { source, markets: HashMap::new() }
+    }
+
+    pub async fn score_wallet(&mut self, wallet: &str, since: i64) -> Result<WhaleStats> {
+        let trades = self.source.get_trades(wallet, since).await
+            .with_context(|| format!("Failed to fetch trades for {}", wallet))?;
+
+        let market_ids: BTreeSet<&str> = trades.iter().map(|t| t.market_id.as_str()).collect();
+        for market_id in market_ids {
+            // Unresolved markets can resolve between refreshes, so only resolved ones are reused
+            if self.markets.get(market_id).is_some_and(|m| m.resolved.is_some()) {
+                continue;
+            }
+            match self.source.get_market(market_id).await {
+                Ok(market) => {
+                    self.markets.insert(market_id.to_string(), market);
+                }
+                Err(e) => tracing::warn!("Scoring {} without market {}: {}", wallet, market_id, e),
+            }
+        }
+
+        Ok(score(wallet, &trades, &self.markets))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::{fixtures, RecordedHistory};
+
+    fn trade(market_id: &str, outcome: Outcome, side: TradeSide, shares: f64, price: f64, timestamp: i64) -> Trade {
+        Trade {
+            outcome,
+            side,
+            shares,
+            price,
+            timestamp,
+            ..fixtures::trade(market_id)
+        }
+    }
+
+    fn market(id: &str, category: &str, resolved: Option<Outcome>, end_date: i64) -> Market {
+        Market {
+            question: format!("Question {}?", id),
+            yes_token_id: format!("{}-yes", id),
+            no_token_id: format!("{}-no", id),
+            end_date: Some(end_date),
+            category: Some(category.to_string()),
+            resolved,
+            ..fixtures::market(id)
+        }
+    }
+
+    fn history() -> RecordedHistory {
+        RecordedHistory::default()
+            // Sold out at a profit: +20 on 40
+            .with_trade(trade("m1", Outcome::Yes, TradeSide::BUY, 100.0, 0.4, 0))
+            .with_trade(trade("m1", Outcome::Yes, TradeSide::SELL, 100.0, 0.6, 3600))
+            // Held to a winning resolution: +25 on 25
+            .with_trade(trade("m2", Outcome::No, TradeSide::BUY, 50.0, 0.5, 100))
+            // Held to a losing resolution: -50 on 50
+            .with_trade(trade("m3", Outcome::Yes, TradeSide::BUY, 100.0, 0.5, 200))
+            // Still open
+            .with_trade(trade("m4", Outcome::Yes, TradeSide::BUY, 10.0, 0.5, 300))
+            .with_market(market("m1", "Politics", None, 100_000))
+            .with_market(market("m2", "Politics", Some(Outcome::No), 7300))
+            .with_market(market("m3", "Sports", Some(Outcome::No), 3800))
+            .with_market(market("m4", "Sports", None, 100_000))
+    }
+
+    #[tokio::test]
+    async fn test_scores_closed_and_resolved_positions() {
+        let history = history();
+        let stats = WhaleScorer::new(&history).score_wallet("0xwhale", 0).await.unwrap();
+
+        assert_eq!(stats.trades, 5);
+        assert_eq!(stats.closed_positions, 3);
+        assert_eq!(stats.open_positions, 1);
+        assert!((stats.win_rate - 2.0 / 3.0).abs() < 1e-9);
+        assert!((stats.realized_pnl + 5.0).abs() < 1e-9);
+        assert!((stats.roi + 5.0 / 115.0).abs() < 1e-9);
+        assert!((stats.avg_holding_secs - 4800.0).abs() < 1e-9);
+        // Returns of +50%, +100% and -100%
+        assert!((stats.consistency - 0.16).abs() < 1e-3);
+
+        assert_eq!(stats.categories[0].category, "Politics");
+        assert_eq!(stats.categories[0].positions, 2);
+        assert!((stats.categories[0].roi - 45.0 / 65.0).abs() < 1e-9);
+        assert_eq!(stats.categories[1].win_rate, 0.0);
+    }
+
+    #[test]
+    fn test_partial_exits_and_unknown_cost_basis() {
+        let trades = vec![
+            // Sold before the window started: nothing to score
+            trade("m0", Outcome::Yes, TradeSide::SELL, 100.0, 0.9, 0),
+            trade("m1", Outcome::Yes, TradeSide::BUY, 100.0, 0.5, 0),
+            trade("m1", Outcome::Yes, TradeSide::SELL, 50.0, 0.7, 10),
+            trade("m1", Outcome::Yes, TradeSide::SELL, 50.0, 0.3, 20),
+        ];
+        let stats = score("0xwhale", &trades, &HashMap::new());
+        assert_eq!(stats.closed_positions, 1);
+        assert!(stats.realized_pnl.abs() < 1e-9);
+        assert_eq!(stats.categories[0].category, UNCATEGORIZED);
+    }
+
+    #[test]
+    fn test_confidence_and_underperformance_need_history() {
+        let stats = WhaleStats { closed_positions: 3, roi: -0.8, ..Default::default() };
+        assert_eq!(stats.confidence(5), 1.0);
+        assert!(stats.underperformance(5, -0.1).is_none());
+
+        assert_eq!(stats.confidence(3), 0.5);
+        assert!(stats.underperformance(3, -0.1).is_some());
+        assert_eq!(WhaleStats { closed_positions: 3, roi: 0.2, ..Default::default() }.confidence(3), 1.2);
+    }
+}
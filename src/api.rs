@@ -1,114 +1,186 @@
-use crate::types::{Market, Trade, OrderRequest, OrderResponse, TradeSide};
-use anyhow::{Context, Result};
-use reqwest::Client;
-use serde_json::json;
+use crate::error::ApiError;
+use crate::ratelimit::{Endpoint, RateLimiter};
+use crate::signals::ClockSkew;
+use crate::types::{Config, Event, Market, Outcome, Trade, OrderRequest, OrderResponse, OrderStatus, TradeSide};
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub type ApiResult<T> = std::result::Result<T, ApiError>;
+
+pub type Orderbook = (Vec<(f64, f64)>, Vec<(f64, f64)>);
+
+// The order-facing subset of the API, so execution can run against a mock exchange
+#[async_trait]
+pub trait Exchange: Send + Sync {
+    async fn place_order(&self, req: OrderRequest, api_key: &str) -> ApiResult<OrderResponse>;
+    async fn get_order(&self, order_id: &str, api_key: &str) -> ApiResult<OrderResponse>;
+    async fn cancel_order(&self, order_id: &str, api_key: &str) -> ApiResult<OrderResponse>;
+    async fn cancel_all(&self, api_key: &str) -> ApiResult<Vec<String>>;
+    async fn get_orderbook(&self, token_id: &str) -> ApiResult<Orderbook>;
+}
+
+// Read-only trade history, so analytics can run against recorded data
+#[async_trait]
+pub trait MarketHistory: Send + Sync {
+    async fn get_trades(&self, wallet: &str, since: i64) -> ApiResult<Vec<Trade>>;
+    async fn get_market(&self, market_id: &str) -> ApiResult<Market>;
+    async fn get_active_markets(&self, limit: usize) -> ApiResult<Vec<Market>>;
+    async fn get_market_trades(&self, market_id: &str, since: i64) -> ApiResult<Vec<Trade>>;
+}
+
+// The reads a copy needs before sizing, so the pipeline can run against a mock exchange
+#[async_trait]
+pub trait MarketData: Send + Sync {
+    async fn get_market(&self, market_id: &str) -> ApiResult<Market>;
+    async fn get_event(&self, event_id: &str) -> ApiResult<Event>;
+    async fn get_balance(&self, wallet: &str) -> ApiResult<f64>;
+    fn clock_skew_ms(&self) -> i64;
+}
 
 #[derive(Clone)]
 pub struct PolymarketApi {
     client: Client,
     base_url: String,
+    clock_skew: Arc<Mutex<ClockSkew>>,
+    limiter: Arc<RateLimiter>,
 }
 
 impl PolymarketApi {
-    pub fn new(base_url: String) -> Self {
-        Self {
-            client: Client::new(),
-            base_url,
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        // HTTP/2 is negotiated over TLS when the server offers it; keep-alive
+        // pings stop idle connections being dropped between whale trades
+        let mut builder = Client::builder()
+            .timeout(Duration::from_millis(config.api_timeout_ms))
+            .connect_timeout(Duration::from_millis(config.api_connect_timeout_ms))
+            .pool_idle_timeout(Duration::from_secs(90))
+            .tcp_nodelay(true)
+            .tcp_keepalive(Duration::from_secs(30))
+            .http2_adaptive_window(true)
+            .http2_keep_alive_interval(Duration::from_secs(20))
+            .http2_keep_alive_while_idle(true);
+        if !config.http_proxy.is_empty() {
+            builder = builder.proxy(reqwest::Proxy::all(&config.http_proxy)?);
         }
+
+        Ok(Self {
+            client: builder.build()?,
+            base_url: config.polymarket_api.clone(),
+            clock_skew: Arc::new(Mutex::new(ClockSkew::new())),
+            limiter: Arc::new(RateLimiter::new(config.rate_limit_headroom)),
+        })
+    }
+
+    // Opens connections ahead of the first trade. Any response will do; only
+    // the handshake matters.
+    pub async fn warm_up(&self, connections: usize) {
+        let started = std::time::Instant::now();
+        let attempts = (0..connections).map(|_| self.client.head(&self.base_url).send());
+        let ok = futures_util::future::join_all(attempts).await
+            .into_iter()
+            .filter(|r| r.is_ok())
+            .count();
+        tracing::info!("Pre-warmed {}/{} API connections in {:?}", ok, connections, started.elapsed());
     }
-    
-    pub async fn get_market(&self, market_id: &str) -> Result<Market> {
+
+    // Local clock minus the exchange's, estimated from response Date headers
+    pub fn clock_skew_ms(&self) -> i64 {
+        self.clock_skew.lock().unwrap().estimate_ms()
+    }
+
+    async fn send_json<T: serde::de::DeserializeOwned>(&self, endpoint: Endpoint, req: RequestBuilder) -> ApiResult<T> {
+        self.limiter.acquire(endpoint).await;
+        let resp = req.send().await?;
+        let status = resp.status();
+
+        let server_time = resp.headers()
+            .get(reqwest::header::DATE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|s| chrono::DateTime::parse_from_rfc2822(s).ok());
+        if let Some(server_time) = server_time {
+            self.clock_skew.lock().unwrap()
+                .observe(chrono::Utc::now().timestamp_millis(), server_time.timestamp_millis());
+        }
+
+        if !status.is_success() {
+            let retry_after = resp.headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string());
+            let body = resp.text().await.unwrap_or_default();
+            let err = ApiError::from_status(status.as_u16(), retry_after.as_deref(), body);
+            if let ApiError::RateLimited { retry_after } = &err {
+                // Hold everyone else off this endpoint too, not just this caller's retry
+                self.limiter.pause(endpoint, retry_after.unwrap_or(Duration::from_secs(1)));
+            }
+            return Err(err);
+        }
+
+        Ok(resp.json::<T>().await?)
+    }
+
+    pub async fn get_market(&self, market_id: &str) -> ApiResult<Market> {
         let url = format!("{}/markets/{}", self.base_url, market_id);
-        let resp = self.client.get(&url)
-            .send()
-            .await
-            .context("Failed to fetch market")?
-            .json::<serde_json::Value>()
-            .await?;
-        
-        Ok(Market {
-            id: market_id.to_string(),
-            event_id: resp["event_id"].as_str().unwrap_or("").to_string(),
-            question: resp["question"].as_str().unwrap_or("").to_string(),
-            yes_price: resp["yes_price"].as_f64().unwrap_or(0.5),
-            no_price: resp["no_price"].as_f64().unwrap_or(0.5),
-            liquidity: resp["liquidity"].as_f64().unwrap_or(0.0),
-            volume_24h: resp["volume_24h"].as_f64().unwrap_or(0.0),
-        })
+        let resp: Value = self.send_json(Endpoint::Markets, self.client.get(&url)).await?;
+        parse_market(market_id, &resp)
+    }
+
+    pub async fn get_event(&self, event_id: &str) -> ApiResult<Event> {
+        let url = format!("{}/events/{}", self.base_url, event_id);
+        let resp: Value = self.send_json(Endpoint::Events, self.client.get(&url)).await?;
+        parse_event(event_id, &resp)
+    }
+
+    pub async fn get_trades(&self, wallet: &str, since: i64) -> ApiResult<Vec<Trade>> {
+        let url = format!("{}/trades", self.base_url);
+        let resp: Vec<Value> = self.send_json(Endpoint::Trades,
+            self.client.get(&url)
+                .query(&[("wallet", wallet), ("since", &since.to_string())])
+        ).await?;
+
+        Ok(parse_list(&resp, "trade", parse_trade))
     }
-    
-    pub async fn get_trades(&self, wallet: &str, since: i64) -> Result<Vec<Trade>> {
+
+    // Open markets, busiest first by 24h volume
+    pub async fn get_active_markets(&self, limit: usize) -> ApiResult<Vec<Market>> {
+        let url = format!("{}/markets", self.base_url);
+        let resp: Vec<Value> = self.send_json(Endpoint::Markets,
+            self.client.get(&url)
+                .query(&[("active", "true"), ("order", "volume_24h"), ("limit", &limit.to_string())])
+        ).await?;
+
+        Ok(parse_list(&resp, "market", |m| parse_market(&required_str(m, "id")?, m)))
+    }
+
+    // Every wallet's fills in one market
+    pub async fn get_market_trades(&self, market_id: &str, since: i64) -> ApiResult<Vec<Trade>> {
         let url = format!("{}/trades", self.base_url);
-        let resp = self.client.get(&url)
-            .query(&[("wallet", wallet), ("since", &since.to_string())])
-            .send()
-            .await
-            .context("Failed to fetch trades")?
-            .json::<Vec<serde_json::Value>>()
-            .await?;
-        
-        let mut trades = Vec::new();
-        for item in resp {
-            trades.push(Trade {
-                wallet: item["wallet"].as_str().unwrap_or("").to_string(),
-                event_id: item["event_id"].as_str().unwrap_or("").to_string(),
-                market_id: item["market_id"].as_str().unwrap_or("").to_string(),
-                side: if item["side"].as_str() == Some("BUY") {
-                    TradeSide::BUY
-                } else {
-                    TradeSide::SELL
-                },
-                shares: item["shares"].as_f64().unwrap_or(0.0),
-                price: item["price"].as_f64().unwrap_or(0.0),
-                timestamp: item["timestamp"].as_i64().unwrap_or(0),
-                tx_hash: item["tx_hash"].as_str().map(|s| s.to_string()),
-            });
-        }
-        
-        Ok(trades)
-    }
-    
-    pub async fn get_orderbook(&self, market_id: &str) -> Result<(Vec<(f64, f64)>, Vec<(f64, f64)>)> {
-        let url = format!("{}/orderbook/{}", self.base_url, market_id);
-        let resp = self.client.get(&url)
-            .send()
-            .await
-            .context("Failed to fetch orderbook")?
-            .json::<serde_json::Value>()
-            .await?;
-        
-        let bids = resp["bids"].as_array()
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|v| {
-                        let price = v["price"].as_f64()?;
-                        let size = v["size"].as_f64()?;
-                        Some((price, size))
-                    })
-                    .collect()
-            })
-            .unwrap_or_default();
-        
-        let asks = resp["asks"].as_array()
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|v| {
-                        let price = v["price"].as_f64()?;
-                        let size = v["size"].as_f64()?;
-                        Some((price, size))
-                    })
-                    .collect()
-            })
-            .unwrap_or_default();
-        
-        Ok((bids, asks))
-    }
-    
-    pub async fn place_order(&self, req: OrderRequest, api_key: &str) -> Result<OrderResponse> {
+        let resp: Vec<Value> = self.send_json(Endpoint::Trades,
+            self.client.get(&url)
+                .query(&[("market", market_id), ("since", &since.to_string())])
+        ).await?;
+
+        Ok(parse_list(&resp, "trade", parse_trade))
+    }
+
+    // Books are per outcome token, not per market
+    pub async fn get_orderbook(&self, token_id: &str) -> ApiResult<Orderbook> {
+        let url = format!("{}/orderbook/{}", self.base_url, token_id);
+        let resp: Value = self.send_json(Endpoint::Orderbook, self.client.get(&url)).await?;
+
+        Ok((parse_levels(&resp, "bids")?, parse_levels(&resp, "asks")?))
+    }
+
+    pub async fn place_order(&self, req: OrderRequest, api_key: &str) -> ApiResult<OrderResponse> {
         let url = format!("{}/orders", self.base_url);
-        
+
         let body = json!({
+            "client_order_id": req.client_order_id,
             "market_id": req.market_id,
+            "token_id": req.token_id,
+            "outcome": req.outcome,
             "side": match req.side {
                 TradeSide::BUY => "BUY",
                 TradeSide::SELL => "SELL",
@@ -116,34 +188,669 @@ impl PolymarketApi {
             "shares": req.shares,
             "price": req.price,
             "type": format!("{:?}", req.order_type),
+            "expiration": req.expiration,
+            "neg_risk": req.neg_risk,
         });
-        
-        let resp = self.client.post(&url)
-            .header("Authorization", format!("Bearer {}", api_key))
-            .json(&body)
-            .send()
-            .await
-            .context("Failed to place order")?
-            .json::<serde_json::Value>()
-            .await?;
-        
-        Ok(OrderResponse {
-            order_id: resp["order_id"].as_str().unwrap_or("").to_string(),
-            status: resp["status"].as_str().unwrap_or("").to_string(),
-            filled_shares: resp["filled_shares"].as_f64().unwrap_or(0.0),
-            avg_fill_price: resp["avg_fill_price"].as_f64().unwrap_or(0.0),
-        })
+
+        let resp: Value = self.send_json(Endpoint::PlaceOrder,
+            self.client.post(&url)
+                .header("Authorization", format!("Bearer {}", api_key))
+                .json(&body)
+        ).await?;
+
+        parse_order_response(&resp)
     }
-    
-    pub async fn get_balance(&self, wallet: &str) -> Result<f64> {
+
+    pub async fn get_order(&self, order_id: &str, api_key: &str) -> ApiResult<OrderResponse> {
+        let url = format!("{}/orders/{}", self.base_url, order_id);
+        let resp: Value = self.send_json(Endpoint::Order,
+            self.client.get(&url)
+                .header("Authorization", format!("Bearer {}", api_key))
+        ).await?;
+
+        parse_order_response(&resp)
+    }
+
+    pub async fn cancel_order(&self, order_id: &str, api_key: &str) -> ApiResult<OrderResponse> {
+        let url = format!("{}/orders/{}", self.base_url, order_id);
+        let resp: Value = self.send_json(Endpoint::Order,
+            self.client.delete(&url)
+                .header("Authorization", format!("Bearer {}", api_key))
+        ).await?;
+
+        parse_order_response(&resp)
+    }
+
+    pub async fn cancel_all(&self, api_key: &str) -> ApiResult<Vec<String>> {
+        let url = format!("{}/orders", self.base_url);
+        let resp: Value = self.send_json(Endpoint::CancelAll,
+            self.client.delete(&url)
+                .header("Authorization", format!("Bearer {}", api_key))
+        ).await?;
+
+        resp["cancelled"].as_array()
+            .ok_or_else(|| ApiError::missing_field("cancelled"))?
+            .iter()
+            .map(|id| id.as_str().map(|s| s.to_string()).ok_or_else(|| ApiError::missing_field("cancelled")))
+            .collect()
+    }
+
+    pub async fn get_balance(&self, wallet: &str) -> ApiResult<f64> {
         let url = format!("{}/balance/{}", self.base_url, wallet);
-        let resp = self.client.get(&url)
-            .send()
-            .await
-            .context("Failed to fetch balance")?
-            .json::<serde_json::Value>()
-            .await?;
-        
-        Ok(resp["balance"].as_f64().unwrap_or(0.0))
+        let resp: Value = self.send_json(Endpoint::Balance, self.client.get(&url)).await?;
+
+        required_f64(&resp, "balance")
+    }
+}
+
+#[async_trait]
+impl Exchange for PolymarketApi {
+    async fn place_order(&self, req: OrderRequest, api_key: &str) -> ApiResult<OrderResponse> {
+        PolymarketApi::place_order(self, req, api_key).await
+    }
+
+    async fn get_order(&self, order_id: &str, api_key: &str) -> ApiResult<OrderResponse> {
+        PolymarketApi::get_order(self, order_id, api_key).await
+    }
+
+    async fn cancel_order(&self, order_id: &str, api_key: &str) -> ApiResult<OrderResponse> {
+        PolymarketApi::cancel_order(self, order_id, api_key).await
+    }
+
+    async fn cancel_all(&self, api_key: &str) -> ApiResult<Vec<String>> {
+        PolymarketApi::cancel_all(self, api_key).await
+    }
+
+    async fn get_orderbook(&self, token_id: &str) -> ApiResult<Orderbook> {
+        PolymarketApi::get_orderbook(self, token_id).await
+    }
+}
+
+#[async_trait]
+impl MarketHistory for PolymarketApi {
+    async fn get_trades(&self, wallet: &str, since: i64) -> ApiResult<Vec<Trade>> {
+        PolymarketApi::get_trades(self, wallet, since).await
+    }
+
+    async fn get_market(&self, market_id: &str) -> ApiResult<Market> {
+        PolymarketApi::get_market(self, market_id).await
+    }
+
+    async fn get_active_markets(&self, limit: usize) -> ApiResult<Vec<Market>> {
+        PolymarketApi::get_active_markets(self, limit).await
+    }
+
+    async fn get_market_trades(&self, market_id: &str, since: i64) -> ApiResult<Vec<Trade>> {
+        PolymarketApi::get_market_trades(self, market_id, since).await
+    }
+}
+
+#[async_trait]
+impl MarketData for PolymarketApi {
+    async fn get_market(&self, market_id: &str) -> ApiResult<Market> {
+        PolymarketApi::get_market(self, market_id).await
+    }
+
+    async fn get_event(&self, event_id: &str) -> ApiResult<Event> {
+        PolymarketApi::get_event(self, event_id).await
+    }
+
+    async fn get_balance(&self, wallet: &str) -> ApiResult<f64> {
+        PolymarketApi::get_balance(self, wallet).await
+    }
+
+    fn clock_skew_ms(&self) -> i64 {
+        PolymarketApi::clock_skew_ms(self)
+    }
+}
+
+fn required_str(v: &Value, field: &str) -> ApiResult<String> {
+    v[field].as_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| ApiError::missing_field(field))
+}
+
+fn required_f64(v: &Value, field: &str) -> ApiResult<f64> {
+    v[field].as_f64()
+        .filter(|x| x.is_finite())
+        .ok_or_else(|| ApiError::missing_field(field))
+}
+
+fn required_price(v: &Value, field: &str) -> ApiResult<f64> {
+    let price = required_f64(v, field)?;
+    if !(0.0..=1.0).contains(&price) {
+        return Err(ApiError::Validation(format!("{} out of range: {}", field, price)));
+    }
+    Ok(price)
+}
+
+fn parse_side(v: &Value) -> ApiResult<TradeSide> {
+    match v["side"].as_str() {
+        Some("BUY") => Ok(TradeSide::BUY),
+        Some("SELL") => Ok(TradeSide::SELL),
+        _ => Err(ApiError::missing_field("side")),
+    }
+}
+
+// Markets list their outcome tokens as [{"token_id": "...", "outcome": "Yes"}, ...]
+fn token_for(v: &Value, outcome: Outcome) -> ApiResult<String> {
+    let field = match outcome {
+        Outcome::Yes => "tokens[YES]",
+        Outcome::No => "tokens[NO]",
+    };
+
+    v["tokens"].as_array()
+        .ok_or_else(|| ApiError::missing_field("tokens"))?
+        .iter()
+        .find(|t| t["outcome"].as_str().and_then(Outcome::parse) == Some(outcome))
+        .ok_or_else(|| ApiError::missing_field(field))
+        .and_then(|t| required_str(t, "token_id"))
+}
+
+pub(crate) fn parse_market(market_id: &str, v: &Value) -> ApiResult<Market> {
+    Ok(Market {
+        id: market_id.to_string(),
+        event_id: required_str(v, "event_id")?,
+        question: required_str(v, "question")?,
+        yes_token_id: token_for(v, Outcome::Yes)?,
+        no_token_id: token_for(v, Outcome::No)?,
+        yes_price: required_price(v, "yes_price")?,
+        no_price: required_price(v, "no_price")?,
+        liquidity: required_f64(v, "liquidity")?,
+        volume_24h: v["volume_24h"].as_f64().unwrap_or(0.0),
+        neg_risk: v["neg_risk"].as_bool().unwrap_or(false),
+        end_date: optional_timestamp(v, "end_date")?,
+        category: v["category"].as_str().map(str::to_string),
+        tags: v["tags"].as_array()
+            .map(|tags| tags.iter().filter_map(|t| t.as_str().map(str::to_string)).collect())
+            .unwrap_or_default(),
+        resolved: match v["resolved_outcome"].as_str() {
+            None => None,
+            Some(raw) => Some(Outcome::parse(raw)
+                .ok_or_else(|| ApiError::Validation(format!("unknown resolved outcome '{}'", raw)))?),
+        },
+    })
+}
+
+// RFC 3339 string to unix seconds; absent is fine, malformed is not
+fn optional_timestamp(v: &Value, field: &str) -> ApiResult<Option<i64>> {
+    match v[field].as_str() {
+        None => Ok(None),
+        Some(raw) => chrono::DateTime::parse_from_rfc3339(raw)
+            .map(|t| Some(t.timestamp()))
+            .map_err(|e| ApiError::Validation(format!("{} '{}' is not a valid date: {}", field, raw, e))),
+    }
+}
+
+pub(crate) fn parse_event(event_id: &str, v: &Value) -> ApiResult<Event> {
+    let markets = v["markets"].as_array()
+        .ok_or_else(|| ApiError::missing_field("markets"))?
+        .iter()
+        .map(|m| {
+            // Nested markets don't repeat their parent's id
+            let mut m = m.clone();
+            m["event_id"] = json!(event_id);
+            parse_market(&required_str(&m, "id")?, &m)
+        })
+        .collect::<ApiResult<Vec<_>>>()?;
+
+    if markets.is_empty() {
+        return Err(ApiError::Validation(format!("event {} has no markets", event_id)));
+    }
+
+    Ok(Event {
+        id: event_id.to_string(),
+        neg_risk: v["neg_risk"].as_bool().unwrap_or(false),
+        markets,
+    })
+}
+
+pub(crate) fn parse_trade(v: &Value) -> ApiResult<Trade> {
+    Ok(Trade {
+        wallet: required_str(v, "wallet")?,
+        event_id: required_str(v, "event_id")?,
+        market_id: required_str(v, "market_id")?,
+        outcome: required_str(v, "outcome").ok()
+            .and_then(|o| Outcome::parse(&o))
+            .ok_or_else(|| ApiError::missing_field("outcome"))?,
+        token_id: v["asset_id"].as_str().map(|s| s.to_string()),
+        side: parse_side(v)?,
+        shares: required_f64(v, "shares")?,
+        price: required_price(v, "price")?,
+        timestamp: v["timestamp"].as_i64().ok_or_else(|| ApiError::missing_field("timestamp"))?,
+        tx_hash: v["tx_hash"].as_str().map(|s| s.to_string()),
+    })
+}
+
+// One malformed item in a history or scan shouldn't cost the rest of the list
+fn parse_list<T>(items: &[Value], kind: &str, parse: impl Fn(&Value) -> ApiResult<T>) -> Vec<T> {
+    items.iter()
+        .filter_map(|item| match parse(item) {
+            Ok(parsed) => Some(parsed),
+            Err(e) => {
+                tracing::warn!("Skipping malformed {}: {}", kind, e);
+                None
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn parse_levels(v: &Value, field: &str) -> ApiResult<Vec<(f64, f64)>> {
+    v[field].as_array()
+        .ok_or_else(|| ApiError::missing_field(field))?
+        .iter()
+        .map(|level| Ok((required_price(level, "price")?, required_f64(level, "size")?)))
+        .collect()
+}
+
+pub(crate) fn parse_order_response(v: &Value) -> ApiResult<OrderResponse> {
+    let raw_status = required_str(v, "status")?;
+    let status = OrderStatus::parse(&raw_status);
+    if status == OrderStatus::Unknown {
+        tracing::warn!("Unrecognised order status from exchange: {}", raw_status);
+    }
+
+    Ok(OrderResponse {
+        order_id: required_str(v, "order_id")?,
+        status,
+        filled_shares: required_f64(v, "filled_shares")?,
+        avg_fill_price: required_f64(v, "avg_fill_price")?,
+        // Fee-free markets omit the field entirely
+        fees: v["fees"].as_f64().unwrap_or(0.0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens() -> Value {
+        json!([
+            {"token_id": "tok-yes", "outcome": "Yes"},
+            {"token_id": "tok-no", "outcome": "No"},
+        ])
+    }
+
+    #[test]
+    fn test_parse_market_tokens() {
+        let resp = json!({
+            "event_id": "event1",
+            "question": "Will it rain?",
+            "tokens": tokens(),
+            "yes_price": 0.6,
+            "no_price": 0.4,
+            "liquidity": 5000.0,
+        });
+
+        let market = parse_market("market1", &resp).unwrap();
+        assert_eq!(market.token_id(Outcome::No), "tok-no");
+        assert_eq!(market.outcome_for_token("tok-yes"), Some(Outcome::Yes));
+        assert_eq!(market.price(Outcome::No), 0.4);
+
+        let missing_no = json!({"tokens": [{"token_id": "tok-yes", "outcome": "Yes"}]});
+        assert!(token_for(&missing_no, Outcome::No).is_err());
+    }
+
+    #[test]
+    fn test_parse_market_metadata() {
+        let mut resp = json!({
+            "event_id": "event1",
+            "question": "Will it rain?",
+            "tokens": tokens(),
+            "yes_price": 0.6,
+            "no_price": 0.4,
+            "liquidity": 5000.0,
+            "end_date": "2024-11-05T23:59:59Z",
+            "category": "Weather",
+            "tags": ["rain", "london"],
+        });
+
+        let market = parse_market("market1", &resp).unwrap();
+        assert_eq!(market.end_date, Some(1730851199));
+        assert_eq!(market.category.as_deref(), Some("Weather"));
+        assert_eq!(market.tags, vec!["rain", "london"]);
+        assert_eq!(market.resolved, None);
+
+        resp["resolved_outcome"] = json!("No");
+        assert_eq!(parse_market("market1", &resp).unwrap().resolved, Some(Outcome::No));
+
+        resp["end_date"] = json!("next tuesday");
+        assert!(matches!(parse_market("market1", &resp), Err(ApiError::Validation(_))));
+    }
+
+    #[test]
+    fn test_parse_neg_risk_event() {
+        let market = |id: &str| json!({
+            "id": id,
+            "question": format!("Will {} win?", id),
+            "tokens": tokens(),
+            "yes_price": 0.3,
+            "no_price": 0.7,
+            "liquidity": 5000.0,
+            "neg_risk": true,
+        });
+        let resp = json!({
+            "neg_risk": true,
+            "markets": [market("alice"), market("bob")],
+        });
+
+        let event = parse_event("election", &resp).unwrap();
+        assert!(event.neg_risk);
+        assert_eq!(event.markets.len(), 2);
+        assert_eq!(event.markets[1].id, "bob");
+        assert_eq!(event.markets[1].event_id, "election");
+    }
+
+    #[test]
+    fn test_parse_market_rejects_missing_price() {
+        let resp = json!({
+            "event_id": "event1",
+            "question": "Will it rain?",
+            "tokens": tokens(),
+            "no_price": 0.4,
+            "liquidity": 5000.0,
+        });
+
+        let err = parse_market("market1", &resp).unwrap_err();
+        assert!(matches!(err, ApiError::Decode(_)));
+        assert!(err.to_string().contains("yes_price"));
+    }
+
+    #[test]
+    fn test_parse_market_rejects_out_of_range_price() {
+        let resp = json!({
+            "event_id": "event1",
+            "question": "Will it rain?",
+            "tokens": tokens(),
+            "yes_price": 1.6,
+            "no_price": 0.4,
+            "liquidity": 5000.0,
+        });
+
+        assert!(matches!(parse_market("market1", &resp), Err(ApiError::Validation(_))));
+    }
+
+    #[test]
+    fn test_parse_orderbook_levels() {
+        let resp = json!({
+            "bids": [{"price": 0.48, "size": 100.0}],
+            "asks": [{"price": 0.52}],
+        });
+
+        assert_eq!(parse_levels(&resp, "bids").unwrap(), vec![(0.48, 100.0)]);
+        assert!(parse_levels(&resp, "asks").is_err());
+    }
+
+    #[test]
+    fn test_trade_list_skips_malformed_items() {
+        let fill = |tx: &str, price: Value| json!({
+            "wallet": "0xwhale",
+            "event_id": "event1",
+            "market_id": "market1",
+            "outcome": "Yes",
+            "side": "BUY",
+            "shares": 100.0,
+            "price": price,
+            "timestamp": 1700000000,
+            "tx_hash": tx,
+        });
+        let resp = [fill("0x1", json!(0.4)), fill("0x2", json!("n/a")), fill("0x3", json!(0.45))];
+
+        let trades = parse_list(&resp, "trade", parse_trade);
+        let hashes: Vec<_> = trades.iter().map(|t| t.tx_hash.as_deref().unwrap()).collect();
+        assert_eq!(hashes, vec!["0x1", "0x3"]);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod mock {
+    use super::*;
+    use std::collections::{HashMap, VecDeque};
+    use std::sync::{Arc, Mutex};
+
+    // Scripted exchange: every call pops the next queued result for that endpoint
+    #[derive(Clone, Default)]
+    pub struct MockExchange {
+        place_results: Arc<Mutex<VecDeque<ApiResult<OrderResponse>>>>,
+        order_results: Arc<Mutex<VecDeque<ApiResult<OrderResponse>>>>,
+        cancel_results: Arc<Mutex<VecDeque<ApiResult<OrderResponse>>>>,
+        placed: Arc<Mutex<Vec<OrderRequest>>>,
+        polled: Arc<Mutex<Vec<String>>>,
+        cancelled: Arc<Mutex<Vec<String>>>,
+        cancel_all_calls: Arc<Mutex<u32>>,
+        orderbook: Arc<Mutex<Orderbook>>,
+        markets: Arc<Mutex<HashMap<String, Market>>>,
+        balances: Arc<Mutex<HashMap<String, f64>>>,
+        market_delay: Arc<Mutex<Duration>>,
+    }
+
+    impl MockExchange {
+        pub fn on_place(&self, result: ApiResult<OrderResponse>) -> &Self {
+            self.place_results.lock().unwrap().push_back(result);
+            self
+        }
+
+        pub fn on_get_order(&self, result: ApiResult<OrderResponse>) -> &Self {
+            self.order_results.lock().unwrap().push_back(result);
+            self
+        }
+
+        // Cancels succeed with nothing filled unless scripted otherwise
+        pub fn on_cancel(&self, result: ApiResult<OrderResponse>) -> &Self {
+            self.cancel_results.lock().unwrap().push_back(result);
+            self
+        }
+
+        pub fn placed(&self) -> Vec<OrderRequest> {
+            self.placed.lock().unwrap().clone()
+        }
+
+        pub fn polled(&self) -> Vec<String> {
+            self.polled.lock().unwrap().clone()
+        }
+
+        pub fn cancelled(&self) -> Vec<String> {
+            self.cancelled.lock().unwrap().clone()
+        }
+
+        pub fn cancel_all_calls(&self) -> u32 {
+            *self.cancel_all_calls.lock().unwrap()
+        }
+
+        // Served for every token; empty until set
+        pub fn with_orderbook(&self, bids: Vec<(f64, f64)>, asks: Vec<(f64, f64)>) -> &Self {
+            *self.orderbook.lock().unwrap() = (bids, asks);
+            self
+        }
+
+        pub fn with_market(&self, market: Market) -> &Self {
+            self.markets.lock().unwrap().insert(market.id.clone(), market);
+            self
+        }
+
+        pub fn with_balance(&self, wallet: &str, balance: f64) -> &Self {
+            self.balances.lock().unwrap().insert(wallet.to_lowercase(), balance);
+            self
+        }
+
+        // Holds every market fetch this long, to keep a copy in flight
+        pub fn with_market_delay(&self, delay: Duration) -> &Self {
+            *self.market_delay.lock().unwrap() = delay;
+            self
+        }
+    }
+
+    pub fn response(order_id: &str, status: OrderStatus, filled_shares: f64, avg_fill_price: f64) -> OrderResponse {
+        OrderResponse {
+            order_id: order_id.to_string(),
+            status,
+            filled_shares,
+            avg_fill_price,
+            fees: 0.0,
+        }
+    }
+
+    #[async_trait]
+    impl Exchange for MockExchange {
+        async fn place_order(&self, req: OrderRequest, _api_key: &str) -> ApiResult<OrderResponse> {
+            self.placed.lock().unwrap().push(req);
+            self.place_results.lock().unwrap()
+                .pop_front()
+                .unwrap_or_else(|| Err(ApiError::Network("no scripted place_order result".to_string())))
+        }
+
+        async fn get_order(&self, order_id: &str, _api_key: &str) -> ApiResult<OrderResponse> {
+            self.polled.lock().unwrap().push(order_id.to_string());
+            self.order_results.lock().unwrap()
+                .pop_front()
+                .unwrap_or_else(|| Err(ApiError::Network("no scripted get_order result".to_string())))
+        }
+
+        async fn cancel_order(&self, order_id: &str, _api_key: &str) -> ApiResult<OrderResponse> {
+            self.cancelled.lock().unwrap().push(order_id.to_string());
+            self.cancel_results.lock().unwrap()
+                .pop_front()
+                .unwrap_or_else(|| Ok(response(order_id, OrderStatus::Cancelled, 0.0, 0.0)))
+        }
+
+        async fn cancel_all(&self, _api_key: &str) -> ApiResult<Vec<String>> {
+            *self.cancel_all_calls.lock().unwrap() += 1;
+            Ok(vec![])
+        }
+
+        async fn get_orderbook(&self, _token_id: &str) -> ApiResult<Orderbook> {
+            Ok(self.orderbook.lock().unwrap().clone())
+        }
+    }
+
+    #[async_trait]
+    impl MarketData for MockExchange {
+        async fn get_market(&self, market_id: &str) -> ApiResult<Market> {
+            let delay = *self.market_delay.lock().unwrap();
+            tokio::time::sleep(delay).await;
+            self.markets.lock().unwrap()
+                .get(market_id)
+                .cloned()
+                .ok_or_else(|| ApiError::Http { status: 404, body: format!("no market {}", market_id) })
+        }
+
+        async fn get_event(&self, event_id: &str) -> ApiResult<Event> {
+            Err(ApiError::Http { status: 404, body: format!("no event {}", event_id) })
+        }
+
+        async fn get_balance(&self, wallet: &str) -> ApiResult<f64> {
+            self.balances.lock().unwrap()
+                .get(&wallet.to_lowercase())
+                .copied()
+                .ok_or_else(|| ApiError::Network(format!("no scripted balance for {}", wallet)))
+        }
+
+        fn clock_skew_ms(&self) -> i64 {
+            0
+        }
+    }
+
+    // Test data shared across modules: a plain binary market and a whale buy
+    // on it. Tests override what they care about with struct update syntax.
+    pub mod fixtures {
+        use crate::types::{Market, Outcome, Trade, TradeSide};
+
+        pub fn market(id: &str) -> Market {
+            Market {
+                id: id.to_string(),
+                event_id: "event1".to_string(),
+                question: "Will it rain?".to_string(),
+                yes_token_id: "tok-yes".to_string(),
+                no_token_id: "tok-no".to_string(),
+                yes_price: 0.5,
+                no_price: 0.5,
+                liquidity: 10_000.0,
+                volume_24h: 0.0,
+                neg_risk: false,
+                end_date: None,
+                category: None,
+                tags: vec![],
+                resolved: None,
+            }
+        }
+
+        pub fn trade(market_id: &str) -> Trade {
+            Trade {
+                wallet: "0xwhale".to_string(),
+                event_id: "event1".to_string(),
+                market_id: market_id.to_string(),
+                outcome: Outcome::Yes,
+                token_id: None,
+                side: TradeSide::BUY,
+                shares: 100.0,
+                price: 0.5,
+                timestamp: 0,
+                tx_hash: None,
+            }
+        }
+    }
+
+    // Recorded trade history and market snapshots, served back as the API would
+    #[derive(Clone, Default)]
+    pub struct RecordedHistory {
+        trades: Vec<Trade>,
+        markets: HashMap<String, Market>,
+    }
+
+    impl RecordedHistory {
+        // Raw API payloads: {"markets": [...], "trades": [...]}
+        pub fn from_recording(recording: &Value) -> ApiResult<Self> {
+            let mut history = Self::default();
+            for m in recording["markets"].as_array().ok_or_else(|| ApiError::missing_field("markets"))? {
+                history = history.with_market(parse_market(&required_str(m, "id")?, m)?);
+            }
+            for t in recording["trades"].as_array().ok_or_else(|| ApiError::missing_field("trades"))? {
+                history = history.with_trade(parse_trade(t)?);
+            }
+            Ok(history)
+        }
+
+        pub fn with_trade(mut self, trade: Trade) -> Self {
+            self.trades.push(trade);
+            self
+        }
+
+        pub fn with_market(mut self, market: Market) -> Self {
+            self.markets.insert(market.id.clone(), market);
+            self
+        }
+    }
+
+    #[async_trait]
+    impl MarketHistory for RecordedHistory {
+        async fn get_trades(&self, wallet: &str, since: i64) -> ApiResult<Vec<Trade>> {
+            Ok(self.trades.iter()
+                .filter(|t| t.wallet.eq_ignore_ascii_case(wallet) && t.timestamp >= since)
+                .cloned()
+                .collect())
+        }
+
+        async fn get_market(&self, market_id: &str) -> ApiResult<Market> {
+            self.markets.get(market_id)
+                .cloned()
+                .ok_or_else(|| ApiError::Http { status: 404, body: format!("no recorded market {}", market_id) })
+        }
+
+        async fn get_active_markets(&self, limit: usize) -> ApiResult<Vec<Market>> {
+            let mut markets: Vec<Market> = self.markets.values()
+                .filter(|m| m.resolved.is_none())
+                .cloned()
+                .collect();
+            markets.sort_by(|a, b| b.volume_24h.total_cmp(&a.volume_24h).then_with(|| a.id.cmp(&b.id)));
+            markets.truncate(limit);
+            Ok(markets)
+        }
+
+        async fn get_market_trades(&self, market_id: &str, since: i64) -> ApiResult<Vec<Trade>> {
+            Ok(self.trades.iter()
+                .filter(|t| t.market_id == market_id && t.timestamp >= since)
+                .cloned()
+                .collect())
+        }
     }
 }
@@ -0,0 +1,43 @@
+use anyhow::{bail, Context, Result};
+use polymarket_copy_bot::{api, config, discovery};
+
+// Usage: discover-whales [--markets N] [--hours N] [--min-fill USD] [--days N]
+//                        [--min-positions N] [--min-roi FRACTION] [--max N]
+// Prints candidate wallets as .env lines to add to the tracked list.
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let config = config::load_config()?;
+    let mut params = discovery::DiscoveryParams {
+        score_window_secs: config.score_lookback_days as i64 * 86400,
+        min_positions: config.min_scored_positions,
+        ..Default::default()
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let value = args.next().with_context(|| format!("{} needs a value", flag))?;
+        let bad = || format!("Invalid value '{}' for {}", value, flag);
+        match flag.as_str() {
+            "--markets" => params.markets = value.parse().with_context(bad)?,
+            "--hours" => params.fill_window_secs = value.parse::<i64>().with_context(bad)? * 3600,
+            "--min-fill" => params.min_fill_usd = value.parse().with_context(bad)?,
+            "--days" => params.score_window_secs = value.parse::<i64>().with_context(bad)? * 86400,
+            "--min-positions" => params.min_positions = value.parse().with_context(bad)?,
+            "--min-roi" => params.min_roi = value.parse().with_context(bad)?,
+            "--max" => params.max_candidates = value.parse().with_context(bad)?,
+            _ => bail!("Unknown option {}", flag),
+        }
+    }
+
+    let api = api::PolymarketApi::new(&config)?;
+    let now = chrono::Utc::now().timestamp();
+    let candidates = discovery::discover(&api, &params, now, &config.wallets_to_track).await?;
+
+    if candidates.is_empty() {
+        tracing::info!("No wallets met the bar; try a longer window or a lower --min-roi");
+    }
+    print!("{}", discovery::render_env(&candidates, &config.wallets_to_track));
+    Ok(())
+}
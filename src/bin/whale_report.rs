@@ -0,0 +1,68 @@
+use anyhow::{Context, Result};
+use polymarket_copy_bot::{analytics, api, config};
+
+// Usage: whale-report [--days N] [--json] [wallet ...]
+// Scores the tracked wallets (or the ones given) over the lookback window.
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let config = config::load_config()?;
+    let mut days = config.score_lookback_days;
+    let mut json = false;
+    let mut wallets = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--days" => {
+                days = args.next()
+                    .context("--days needs a value")?
+                    .parse()
+                    .context("--days must be a whole number")?;
+            }
+            "--json" => json = true,
+            _ => wallets.push(arg),
+        }
+    }
+    if wallets.is_empty() {
+        wallets = config.wallets_to_track.clone();
+    }
+
+    let api = api::PolymarketApi::new(&config)?;
+    let mut scorer = analytics::WhaleScorer::new(&api);
+    let since = chrono::Utc::now().timestamp() - days as i64 * 86400;
+
+    let mut report = Vec::new();
+    for wallet in &wallets {
+        match scorer.score_wallet(wallet, since).await {
+            Ok(stats) => report.push(stats),
+            Err(e) => tracing::error!("{:#}", e),
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("Whale report, last {} days\n", days);
+    for stats in &report {
+        println!("{}", stats.wallet);
+        println!("   Trades: {}  closed: {}  open: {}", stats.trades, stats.closed_positions, stats.open_positions);
+        println!("   Win rate: {:.1}%  ROI: {:+.1}%  PnL: ${:+.2} on ${:.2}",
+            stats.win_rate * 100.0, stats.roi * 100.0, stats.realized_pnl, stats.invested);
+        println!("   Avg hold: {:.1}h  consistency: {:.2}", stats.avg_holding_secs / 3600.0, stats.consistency);
+        println!("   Confidence: x{:.2}", stats.confidence(config.min_scored_positions));
+        if let Some(reason) = stats.underperformance(config.min_scored_positions, config.min_wallet_roi) {
+            println!("   ⚠️  Would be disabled: {}", reason);
+        }
+        for category in &stats.categories {
+            println!("     {:<20} {:>3} positions  win {:>5.1}%  ROI {:+.1}%",
+                category.category, category.positions, category.win_rate * 100.0, category.roi * 100.0);
+        }
+        println!();
+    }
+
+    Ok(())
+}
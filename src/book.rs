@@ -0,0 +1,349 @@
+use crate::api::{parse_levels, Orderbook};
+use crate::types::{Market, TradeSide};
+use anyhow::{Context, Result};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Mutex, RwLock};
+use tokio::sync::Notify;
+use tokio::time::{Duration, Instant};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+// Prices keyed in millionths so levels sort and compare exactly
+type PriceKey = u64;
+
+fn key(price: f64) -> PriceKey {
+    (price * 1_000_000.0).round() as PriceKey
+}
+
+fn price(key: PriceKey) -> f64 {
+    key as f64 / 1_000_000.0
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceGap {
+    pub expected: u64,
+    pub got: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct L2Book {
+    bids: BTreeMap<PriceKey, f64>,
+    asks: BTreeMap<PriceKey, f64>,
+    seq: u64,
+}
+
+impl L2Book {
+    pub fn from_snapshot(seq: u64, bids: &[(f64, f64)], asks: &[(f64, f64)]) -> Self {
+        let mut book = Self { seq, ..Default::default() };
+        book.update(bids, asks);
+        book
+    }
+
+    // Deltas carry absolute sizes for the levels that changed; zero removes
+    // the level. Anything at or before our sequence is a replay and ignored.
+    pub fn apply_delta(&mut self, seq: u64, bids: &[(f64, f64)], asks: &[(f64, f64)]) -> Result<(), SequenceGap> {
+        if seq <= self.seq {
+            return Ok(());
+        }
+        if seq != self.seq + 1 {
+            return Err(SequenceGap { expected: self.seq + 1, got: seq });
+        }
+        self.update(bids, asks);
+        self.seq = seq;
+        Ok(())
+    }
+
+    fn update(&mut self, bids: &[(f64, f64)], asks: &[(f64, f64)]) {
+        for (side, levels) in [(&mut self.bids, bids), (&mut self.asks, asks)] {
+            for &(p, size) in levels {
+                if size > 0.0 {
+                    side.insert(key(p), size);
+                } else {
+                    side.remove(&key(p));
+                }
+            }
+        }
+    }
+
+    pub fn best_bid(&self) -> Option<f64> {
+        self.bids.keys().next_back().copied().map(price)
+    }
+
+    pub fn best_ask(&self) -> Option<f64> {
+        self.asks.keys().next().copied().map(price)
+    }
+
+    // Top of the book we'd trade against
+    pub fn best(&self, side: &TradeSide) -> Option<f64> {
+        match side {
+            TradeSide::BUY => self.best_ask(),
+            TradeSide::SELL => self.best_bid(),
+        }
+    }
+
+    pub fn mid(&self) -> Option<f64> {
+        Some((self.best_bid()? + self.best_ask()?) / 2.0)
+    }
+
+    // Same shape as the REST endpoint: bids high to low, asks low to high
+    pub fn levels(&self) -> Orderbook {
+        (
+            self.bids.iter().rev().map(|(&k, &s)| (price(k), s)).collect(),
+            self.asks.iter().map(|(&k, &s)| (price(k), s)).collect(),
+        )
+    }
+}
+
+#[derive(Debug)]
+enum BookMessage {
+    Snapshot { token_id: String, seq: u64, bids: Vec<(f64, f64)>, asks: Vec<(f64, f64)> },
+    Delta { token_id: String, seq: u64, bids: Vec<(f64, f64)>, asks: Vec<(f64, f64)> },
+}
+
+fn parse_book_message(event: &Value) -> Option<BookMessage> {
+    let token_id = event["asset_id"].as_str()?.to_string();
+    let seq = event["seq"].as_u64()?;
+    // A delta may only touch one side
+    let levels = |field| if event[field].is_null() { Some(vec![]) } else { parse_levels(event, field).ok() };
+
+    match event["event_type"].as_str()? {
+        "book" => Some(BookMessage::Snapshot {
+            token_id,
+            seq,
+            bids: parse_levels(event, "bids").ok()?,
+            asks: parse_levels(event, "asks").ok()?,
+        }),
+        "price_change" => Some(BookMessage::Delta { token_id, seq, bids: levels("bids")?, asks: levels("asks")? }),
+        _ => None,
+    }
+}
+
+// In-memory mirror of the CLOB market channel for every token we've been
+// asked to watch. A book is only served while it's in sync: after a
+// sequence gap or a dropped connection it's discarded until a fresh
+// snapshot arrives, and callers fall back to REST in the meantime.
+#[derive(Debug, Default)]
+pub struct OrderBooks {
+    books: RwLock<HashMap<String, L2Book>>,
+    watched: Mutex<HashSet<String>>,  // Tokens
+    markets: Mutex<HashMap<String, Market>>,  // Watched markets, for their token ids
+    changed: Notify,
+}
+
+impl OrderBooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn watch_market(&self, market: &Market) {
+        self.markets.lock().unwrap().insert(market.id.clone(), market.clone());
+        let mut watched = self.watched.lock().unwrap();
+        let added = [&market.yes_token_id, &market.no_token_id].into_iter()
+            .filter(|t| !t.is_empty() && watched.insert((*t).clone()))
+            .count();
+        if added > 0 {
+            self.changed.notify_one();
+        }
+    }
+
+    pub fn with_book<R>(&self, token_id: &str, f: impl FnOnce(&L2Book) -> R) -> Option<R> {
+        self.books.read().unwrap().get(token_id).map(f)
+    }
+
+    // A watched market priced at its book mids, when both outcomes are in sync
+    pub fn mark(&self, market_id: &str) -> Option<Market> {
+        let market = self.markets.lock().unwrap().get(market_id)?.clone();
+        let yes = self.with_book(&market.yes_token_id, L2Book::mid)??;
+        let no = self.with_book(&market.no_token_id, L2Book::mid)??;
+        Some(Market { yes_price: yes, no_price: no, ..market })
+    }
+
+    // Returns a token whose book fell out of sync and needs a new snapshot
+    fn handle(&self, event: &Value) -> Option<String> {
+        match parse_book_message(event)? {
+            BookMessage::Snapshot { token_id, seq, bids, asks } => {
+                self.books.write().unwrap().insert(token_id, L2Book::from_snapshot(seq, &bids, &asks));
+                None
+            }
+            BookMessage::Delta { token_id, seq, bids, asks } => {
+                let mut books = self.books.write().unwrap();
+                // Deltas before the first snapshot have nothing to apply to
+                let book = books.get_mut(&token_id)?;
+                if let Err(gap) = book.apply_delta(seq, &bids, &asks) {
+                    tracing::warn!("Order book {} out of sync (expected seq {}, got {}), resubscribing",
+                        token_id, gap.expected, gap.got);
+                    books.remove(&token_id);
+                    return Some(token_id);
+                }
+                None
+            }
+        }
+    }
+
+    pub async fn run(self: std::sync::Arc<Self>, ws_url: String, idle_timeout: Duration) {
+        loop {
+            match self.connect_and_mirror(&ws_url, idle_timeout).await {
+                Ok(_) => tracing::info!("Order book WebSocket closed"),
+                Err(e) => tracing::error!("Order book WebSocket error: {}", e),
+            }
+            // Whatever we missed while disconnected makes every book suspect
+            self.books.write().unwrap().clear();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+    }
+
+    // A half-open connection can sit silent forever, serving books that have
+    // stopped updating. Pings every half timeout draw a pong from a live
+    // feed, so silence past the timeout ends the connection.
+    async fn connect_and_mirror(&self, ws_url: &str, idle_timeout: Duration) -> Result<()> {
+        let (ws_stream, _) = connect_async(ws_url)
+            .await
+            .context("Failed to connect to WebSocket")?;
+        let (mut write, mut read) = ws_stream.split();
+        let mut subscribed: HashSet<String> = HashSet::new();
+        let mut ping = tokio::time::interval(idle_timeout / 2);
+        let idle = tokio::time::sleep(idle_timeout);
+        tokio::pin!(idle);
+
+        loop {
+            // Subscribing (again) makes the exchange send a fresh snapshot
+            let pending: Vec<String> = self.watched.lock().unwrap()
+                .difference(&subscribed)
+                .cloned()
+                .collect();
+            if !pending.is_empty() {
+                let subscribe_msg = json!({
+                    "type": "subscribe",
+                    "channel": "market",
+                    "assets_ids": pending,
+                });
+                write.send(Message::Text(subscribe_msg.to_string()))
+                    .await
+                    .context("Failed to send subscribe message")?;
+                tracing::info!("Subscribed to {} order books", pending.len());
+                subscribed.extend(pending);
+            }
+
+            tokio::select! {
+                _ = self.changed.notified() => {}
+                _ = ping.tick() => {
+                    write.send(Message::Ping(vec![])).await.context("Failed to send ping")?;
+                }
+                _ = &mut idle => anyhow::bail!("No messages for {:?}", idle_timeout),
+                msg = read.next() => {
+                    // Pongs count too: the feed is alive, just quiet
+                    idle.as_mut().reset(Instant::now() + idle_timeout);
+                    match msg {
+                        Some(Ok(Message::Text(text))) => {
+                            let Ok(event) = serde_json::from_str::<Value>(&text) else { continue };
+                            // The channel batches several updates into one array
+                            let events = match event {
+                                Value::Array(events) => events,
+                                event => vec![event],
+                            };
+                            for event in &events {
+                                if let Some(token_id) = self.handle(event) {
+                                    subscribed.remove(&token_id);
+                                }
+                            }
+                        }
+                        Some(Ok(Message::Close(_))) | None => return Ok(()),
+                        Some(Err(e)) => return Err(e.into()),
+                        Some(Ok(_)) => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::fixtures;
+
+    fn snapshot(seq: u64) -> Value {
+        json!({
+            "event_type": "book",
+            "asset_id": "tok-yes",
+            "seq": seq,
+            "bids": [{"price": 0.48, "size": 100.0}, {"price": 0.47, "size": 50.0}],
+            "asks": [{"price": 0.52, "size": 80.0}, {"price": 0.55, "size": 200.0}],
+        })
+    }
+
+    fn delta(seq: u64, asks: Value) -> Value {
+        json!({"event_type": "price_change", "asset_id": "tok-yes", "seq": seq, "asks": asks})
+    }
+
+    #[test]
+    fn test_snapshot_then_deltas() {
+        let books = OrderBooks::new();
+        assert!(books.handle(&snapshot(10)).is_none());
+        assert_eq!(books.with_book("tok-yes", |b| b.best(&TradeSide::BUY)), Some(Some(0.52)));
+
+        // Best ask lifted, a new level behind it
+        books.handle(&delta(11, json!([{"price": 0.52, "size": 0.0}, {"price": 0.53, "size": 40.0}])));
+        // A replay of an old update changes nothing
+        books.handle(&delta(11, json!([{"price": 0.51, "size": 10.0}])));
+
+        let (bids, asks) = books.with_book("tok-yes", L2Book::levels).unwrap();
+        assert_eq!(bids, vec![(0.48, 100.0), (0.47, 50.0)]);
+        assert_eq!(asks, vec![(0.53, 40.0), (0.55, 200.0)]);
+        assert_eq!(books.with_book("tok-yes", L2Book::mid), Some(Some(0.505)));
+    }
+
+    #[test]
+    fn test_sequence_gap_drops_book_until_resnapshot() {
+        let books = OrderBooks::new();
+        // Nothing to apply a delta to before the snapshot
+        assert!(books.handle(&delta(1, json!([]))).is_none());
+
+        books.handle(&snapshot(10));
+        assert_eq!(books.handle(&delta(12, json!([]))), Some("tok-yes".to_string()));
+        assert!(books.with_book("tok-yes", L2Book::mid).is_none());
+
+        books.handle(&snapshot(20));
+        assert!(books.handle(&delta(21, json!([]))).is_none());
+        assert!(books.with_book("tok-yes", L2Book::mid).is_some());
+    }
+
+    #[test]
+    fn test_mark_needs_both_outcomes() {
+        let books = OrderBooks::new();
+        let market = Market {
+            yes_price: 0.6,
+            no_price: 0.4,
+            liquidity: 5000.0,
+            ..fixtures::market("market1")
+        };
+        books.watch_market(&market);
+        assert_eq!(books.watched.lock().unwrap().len(), 2);
+
+        books.handle(&snapshot(1));
+        assert!(books.mark("market1").is_none());
+
+        let mut no_book = snapshot(1);
+        no_book["asset_id"] = json!("tok-no");
+        books.handle(&no_book);
+        let marked = books.mark("market1").unwrap();
+        assert_eq!((marked.yes_price, marked.no_price), (0.5, 0.5));
+    }
+
+    #[tokio::test]
+    async fn test_silent_feed_times_out() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        // Completes the handshake, then never sends or reads a thing
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let _ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            std::future::pending::<()>().await;
+        });
+
+        let books = OrderBooks::new();
+        let err = books.connect_and_mirror(&url, Duration::from_millis(100)).await.unwrap_err();
+        assert!(err.to_string().contains("No messages"), "{}", err);
+    }
+}
@@ -0,0 +1,384 @@
+use crate::types::Config;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TripReason {
+    ConsecutiveErrors { count: u32 },
+    ErrorRate { errors: usize, samples: usize, window_secs: u64 },
+    DailyLoss { loss: f64, limit: f64 },
+    Drawdown { peak: f64, equity: f64, pct: f64 },
+    LatencySpike { latency_ms: u64, limit_ms: u64 },
+    Slippage { market_id: String, bps: f64, limit_bps: f64 },
+    ProbeFailed { error: String },
+    Manual { note: String },
+}
+
+impl fmt::Display for TripReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TripReason::ConsecutiveErrors { count } =>
+                write!(f, "Too many consecutive errors: {}", count),
+            TripReason::ErrorRate { errors, samples, window_secs } =>
+                write!(f, "Error rate {}/{} over the last {}s", errors, samples, window_secs),
+            TripReason::DailyLoss { loss, limit } =>
+                write!(f, "Daily loss ${:.2} exceeds ${:.2}", loss, limit),
+            TripReason::Drawdown { peak, equity, pct } =>
+                write!(f, "Drawdown {:.1}% from peak equity ${:.2} (now ${:.2})", pct * 100.0, peak, equity),
+            TripReason::LatencySpike { latency_ms, limit_ms } =>
+                write!(f, "Execution latency {}ms exceeds {}ms", latency_ms, limit_ms),
+            TripReason::Slippage { market_id, bps, limit_bps } =>
+                write!(f, "Slippage {:.0}bps on {} exceeds {:.0}bps", bps, market_id, limit_bps),
+            TripReason::ProbeFailed { error } =>
+                write!(f, "Half-open probe failed: {}", error),
+            TripReason::Manual { note } =>
+                write!(f, "Tripped manually: {}", note),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BreakerPhase {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug, Clone)]
+enum Phase {
+    Closed,
+    Open { since: Instant },
+    HalfOpen { in_flight: u32, succeeded: u32 },
+}
+
+// Closed -> Open on any trip condition. After the cooldown the breaker goes
+// half-open and lets `cb_probe_trades` trades through; if they all succeed it
+// closes, and any failure re-opens it for another cooldown.
+pub struct CircuitBreaker {
+    config: Config,
+    phase: Phase,
+    reason: Option<TripReason>,
+    consecutive_errors: u32,
+    outcomes: VecDeque<(Instant, bool)>,  // (when, succeeded) over the error-rate window
+    equity_peak: f64,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            phase: Phase::Closed,
+            reason: None,
+            consecutive_errors: 0,
+            outcomes: VecDeque::new(),
+            equity_peak: 0.0,
+        }
+    }
+
+    pub fn phase(&self) -> BreakerPhase {
+        match self.phase {
+            Phase::Closed => BreakerPhase::Closed,
+            Phase::Open { .. } => BreakerPhase::Open,
+            Phase::HalfOpen { .. } => BreakerPhase::HalfOpen,
+        }
+    }
+
+    pub fn is_tripped(&self) -> bool {
+        !matches!(self.phase, Phase::Closed)
+    }
+
+    pub fn reason(&self) -> Option<&TripReason> {
+        self.reason.as_ref()
+    }
+
+    pub fn consecutive_errors(&self) -> u32 {
+        self.consecutive_errors
+    }
+
+    // Returns Ok when a trade may go ahead. In half-open this hands out a probe
+    // slot, which must be settled with on_success or on_error.
+    pub fn allow_trade(&mut self, now: Instant) -> Result<(), TripReason> {
+        let probes = self.config.cb_probe_trades.max(1);
+
+        match &mut self.phase {
+            Phase::Closed => Ok(()),
+            Phase::Open { since } => {
+                if now.duration_since(*since) < Duration::from_secs(self.config.cb_cooldown_secs) {
+                    return Err(self.current_reason());
+                }
+                tracing::warn!("Circuit breaker cooldown elapsed, going half-open for a probe trade");
+                self.phase = Phase::HalfOpen { in_flight: 1, succeeded: 0 };
+                Ok(())
+            }
+            Phase::HalfOpen { in_flight, succeeded } => {
+                if *in_flight + *succeeded >= probes {
+                    return Err(self.current_reason());
+                }
+                *in_flight += 1;
+                tracing::info!("Circuit breaker half-open: allowing probe trade");
+                Ok(())
+            }
+        }
+    }
+
+    pub fn on_success(&mut self, now: Instant) {
+        self.consecutive_errors = 0;
+        self.push_outcome(now, true);
+
+        if let Phase::HalfOpen { in_flight, succeeded } = &mut self.phase {
+            *in_flight = in_flight.saturating_sub(1);
+            *succeeded += 1;
+            if *succeeded >= self.config.cb_probe_trades.max(1) {
+                self.close();
+            }
+        }
+    }
+
+    // A failed order. Returns the trip reason if this error opened the breaker.
+    pub fn on_error(&mut self, now: Instant, error: &str) -> Option<TripReason> {
+        if matches!(self.phase, Phase::HalfOpen { .. }) {
+            self.consecutive_errors += 1;
+            self.push_outcome(now, false);
+            return self.trip(now, TripReason::ProbeFailed { error: error.to_string() });
+        }
+        self.on_failure(now)
+    }
+
+    // A failure before any order went out, e.g. fetching the market or a
+    // balance. Only a probe's own order settles it, so these count toward
+    // the closed-state limits and are otherwise ignored.
+    pub fn on_data_error(&mut self, now: Instant) -> Option<TripReason> {
+        if !matches!(self.phase, Phase::Closed) {
+            return None;
+        }
+        self.on_failure(now)
+    }
+
+    fn on_failure(&mut self, now: Instant) -> Option<TripReason> {
+        self.consecutive_errors += 1;
+        self.push_outcome(now, false);
+
+        if self.consecutive_errors >= self.config.cb_consecutive_trigger {
+            return self.trip(now, TripReason::ConsecutiveErrors { count: self.consecutive_errors });
+        }
+
+        let samples = self.outcomes.len();
+        let errors = self.outcomes.iter().filter(|(_, ok)| !ok).count();
+        if samples >= self.config.cb_error_rate_min_samples as usize
+            && errors as f64 / samples as f64 >= self.config.cb_error_rate_threshold
+        {
+            return self.trip(now, TripReason::ErrorRate {
+                errors,
+                samples,
+                window_secs: self.config.cb_error_window_secs,
+            });
+        }
+
+        None
+    }
+
+    pub fn on_latency(&mut self, now: Instant, latency: Duration) -> Option<TripReason> {
+        let latency_ms = latency.as_millis() as u64;
+        if latency_ms > self.config.cb_latency_spike_ms {
+            return self.trip(now, TripReason::LatencySpike {
+                latency_ms,
+                limit_ms: self.config.cb_latency_spike_ms,
+            });
+        }
+        None
+    }
+
+    // Adverse slippage only: filling better than expected is never a problem
+    pub fn on_slippage(&mut self, now: Instant, market_id: &str, bps: f64) -> Option<TripReason> {
+        if bps > self.config.cb_max_slippage_bps {
+            return self.trip(now, TripReason::Slippage {
+                market_id: market_id.to_string(),
+                bps,
+                limit_bps: self.config.cb_max_slippage_bps,
+            });
+        }
+        None
+    }
+
+    // Reaching a loss limit counts as breaching it, here and in the kill switch
+    pub fn on_daily_pnl(&mut self, now: Instant, pnl_today: f64) -> Option<TripReason> {
+        let loss = -pnl_today;
+        if loss >= self.config.cb_max_daily_loss {
+            return self.trip(now, TripReason::DailyLoss { loss, limit: self.config.cb_max_daily_loss });
+        }
+        None
+    }
+
+    pub fn on_equity(&mut self, now: Instant, equity: f64) -> Option<TripReason> {
+        self.equity_peak = self.equity_peak.max(equity);
+        if self.equity_peak <= 0.0 {
+            return None;
+        }
+
+        let pct = (self.equity_peak - equity) / self.equity_peak;
+        if pct >= self.config.cb_max_drawdown_pct {
+            return self.trip(now, TripReason::Drawdown { peak: self.equity_peak, equity, pct });
+        }
+        None
+    }
+
+    pub fn trip(&mut self, now: Instant, reason: TripReason) -> Option<TripReason> {
+        if matches!(self.phase, Phase::Open { .. }) {
+            return None;
+        }
+
+        tracing::error!("CIRCUIT BREAKER TRIPPED: {}", reason);
+        self.phase = Phase::Open { since: now };
+        self.reason = Some(reason.clone());
+        Some(reason)
+    }
+
+    pub fn reset(&mut self) {
+        self.close();
+        self.outcomes.clear();
+    }
+
+    fn close(&mut self) {
+        if self.is_tripped() {
+            tracing::info!("Circuit breaker closed");
+        }
+        self.phase = Phase::Closed;
+        self.reason = None;
+        self.consecutive_errors = 0;
+    }
+
+    fn current_reason(&self) -> TripReason {
+        self.reason.clone().unwrap_or(TripReason::Manual { note: "unknown".to_string() })
+    }
+
+    fn push_outcome(&mut self, now: Instant, ok: bool) {
+        let window = Duration::from_secs(self.config.cb_error_window_secs);
+        self.outcomes.push_back((now, ok));
+        while let Some((t, _)) = self.outcomes.front() {
+            if now.duration_since(*t) > window {
+                self.outcomes.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker() -> CircuitBreaker {
+        CircuitBreaker::new(Config {
+            cb_consecutive_trigger: 3,
+            cb_cooldown_secs: 60,
+            cb_probe_trades: 2,
+            cb_error_rate_threshold: 0.5,
+            cb_error_rate_min_samples: 6,
+            cb_error_window_secs: 300,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_half_open_recovery() {
+        let mut cb = breaker();
+        let t0 = Instant::now();
+        for _ in 0..3 {
+            cb.on_error(t0, "boom");
+        }
+        assert_eq!(cb.phase(), BreakerPhase::Open);
+        assert!(matches!(cb.allow_trade(t0 + Duration::from_secs(30)), Err(TripReason::ConsecutiveErrors { count: 3 })));
+
+        // Cooldown over: two probes allowed, a third has to wait for results
+        let t1 = t0 + Duration::from_secs(61);
+        assert!(cb.allow_trade(t1).is_ok());
+        assert_eq!(cb.phase(), BreakerPhase::HalfOpen);
+        assert!(cb.allow_trade(t1).is_ok());
+        assert!(cb.allow_trade(t1).is_err());
+
+        cb.on_success(t1);
+        assert_eq!(cb.phase(), BreakerPhase::HalfOpen);
+        cb.on_success(t1);
+        assert_eq!(cb.phase(), BreakerPhase::Closed);
+        assert!(cb.reason().is_none());
+    }
+
+    #[test]
+    fn test_failed_probe_reopens() {
+        let mut cb = breaker();
+        let t0 = Instant::now();
+        cb.on_latency(t0, Duration::from_secs(60));
+        assert_eq!(cb.phase(), BreakerPhase::Open);
+
+        let t1 = t0 + Duration::from_secs(61);
+        assert!(cb.allow_trade(t1).is_ok());
+        assert!(matches!(cb.on_error(t1, "rejected"), Some(TripReason::ProbeFailed { .. })));
+
+        // Fresh cooldown from the failed probe
+        assert!(cb.allow_trade(t1 + Duration::from_secs(30)).is_err());
+        assert!(cb.allow_trade(t1 + Duration::from_secs(61)).is_ok());
+    }
+
+    #[test]
+    fn test_data_errors_dont_settle_probes() {
+        let mut cb = breaker();
+        let t0 = Instant::now();
+        for _ in 0..3 {
+            cb.on_data_error(t0);
+        }
+        assert_eq!(cb.phase(), BreakerPhase::Open);
+
+        // A balance fetch failing while half-open neither fails the probe nor
+        // restarts the cooldown
+        let t1 = t0 + Duration::from_secs(61);
+        assert!(cb.allow_trade(t1).is_ok());
+        assert!(cb.on_data_error(t1).is_none());
+        assert_eq!(cb.phase(), BreakerPhase::HalfOpen);
+
+        cb.on_success(t1);
+        cb.on_success(t1);
+        assert_eq!(cb.phase(), BreakerPhase::Closed);
+    }
+
+    #[test]
+    fn test_error_rate_trips_without_consecutive_run() {
+        let mut cb = breaker();
+        let t0 = Instant::now();
+        for i in 0..6 {
+            if i % 2 == 1 {
+                cb.on_error(t0, "boom");
+            } else {
+                cb.on_success(t0);
+            }
+        }
+        assert!(matches!(cb.reason(), Some(TripReason::ErrorRate { errors: 3, samples: 6, .. })));
+    }
+
+    #[test]
+    fn test_loss_drawdown_and_slippage_triggers() {
+        let now = Instant::now();
+        let config = Config {
+            cb_max_daily_loss: 100.0,
+            cb_max_drawdown_pct: 0.2,
+            cb_max_slippage_bps: 200.0,
+            ..Default::default()
+        };
+
+        let mut cb = CircuitBreaker::new(config.clone());
+        assert!(cb.on_daily_pnl(now, -50.0).is_none());
+        assert!(matches!(cb.on_daily_pnl(now, -100.0), Some(TripReason::DailyLoss { .. })));
+
+        let mut cb = CircuitBreaker::new(config.clone());
+        cb.on_equity(now, 1000.0);
+        cb.on_equity(now, 1200.0);
+        assert!(cb.on_equity(now, 1000.0).is_none());
+        assert!(matches!(cb.on_equity(now, 950.0), Some(TripReason::Drawdown { .. })));
+
+        let mut cb = CircuitBreaker::new(config);
+        assert!(cb.on_slippage(now, "market1", 150.0).is_none());
+        assert!(matches!(cb.on_slippage(now, "market1", 250.0), Some(TripReason::Slippage { .. })));
+    }
+}
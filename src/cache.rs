@@ -0,0 +1,247 @@
+use crate::api::{ApiResult, MarketData};
+use crate::types::{Config, Event, Market};
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+struct Entry<V> {
+    value: V,
+    expires: Instant,
+    last_used: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub len: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+// Expiring, size-bounded cache. Concurrent misses on the same key wait for a
+// single fetch instead of each going to the network; when full, the least
+// recently used entry is evicted.
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<HashMap<K, Entry<V>>>,
+    in_flight: Mutex<HashMap<K, Arc<tokio::sync::Mutex<()>>>>,
+    clock: AtomicU64,  // Logical time for LRU order
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> TtlCache<K, V> {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity: capacity.max(1),
+            entries: Mutex::new(HashMap::new()),
+            in_flight: Mutex::new(HashMap::new()),
+            clock: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get_mut(key) {
+            Some(entry) if entry.expires > Instant::now() => {
+                entry.last_used = self.clock.fetch_add(1, Ordering::Relaxed);
+                Some(entry.value.clone())
+            }
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            let now = Instant::now();
+            entries.retain(|_, e| e.expires > now);
+            if entries.len() >= self.capacity {
+                let lru = entries.iter().min_by_key(|(_, e)| e.last_used).map(|(k, _)| k.clone());
+                if let Some(lru) = lru {
+                    entries.remove(&lru);
+                }
+            }
+        }
+        entries.insert(key, Entry {
+            value,
+            expires: Instant::now() + self.ttl,
+            last_used: self.clock.fetch_add(1, Ordering::Relaxed),
+        });
+    }
+
+    pub fn invalidate(&self, key: &K) {
+        self.entries.lock().unwrap().remove(key);
+    }
+
+    pub async fn get_or_fetch<F, Fut, E>(&self, key: &K, fetch: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+    {
+        if let Some(value) = self.get(key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(value);
+        }
+
+        let gate = Arc::clone(self.in_flight.lock().unwrap().entry(key.clone()).or_default());
+        let turn = gate.lock().await;
+
+        // Whoever held the gate before us may have filled it
+        let result = match self.get(key) {
+            Some(value) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Ok(value)
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                let result = fetch().await;
+                if let Ok(value) = &result {
+                    self.insert(key.clone(), value.clone());
+                }
+                result
+            }
+        };
+        drop(turn);
+
+        // Last one through cleans up; the map's reference plus ours means nobody is waiting
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if Arc::strong_count(&gate) <= 2 {
+            in_flight.remove(key);
+        }
+        result
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            len: self.entries.lock().unwrap().len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// Read-through cache over the market, event and balance endpoints. Our own
+// balance isn't cached here: RiskManager tracks it from fills and it is
+// reconciled against the exchange on a timer.
+#[derive(Clone)]
+pub struct CachedApi {
+    api: Arc<dyn MarketData>,
+    markets: Arc<TtlCache<String, Market>>,
+    events: Arc<TtlCache<String, Event>>,
+    balances: Arc<TtlCache<String, f64>>,
+}
+
+impl CachedApi {
+    pub fn new(api: impl MarketData + 'static, config: &Config) -> Self {
+        Self {
+            api: Arc::new(api),
+            markets: Arc::new(TtlCache::new(Duration::from_millis(config.market_cache_ttl_ms), config.cache_capacity)),
+            events: Arc::new(TtlCache::new(Duration::from_secs(config.event_cache_ttl_secs), config.cache_capacity)),
+            balances: Arc::new(TtlCache::new(Duration::from_secs(config.balance_cache_ttl_secs), config.cache_capacity)),
+        }
+    }
+
+    // Straight from the exchange, for our own balance
+    pub async fn fetch_balance(&self, wallet: &str) -> ApiResult<f64> {
+        self.api.get_balance(wallet).await
+    }
+
+    pub async fn get_market(&self, market_id: &str) -> ApiResult<Market> {
+        self.markets.get_or_fetch(&market_id.to_string(), || self.api.get_market(market_id)).await
+    }
+
+    pub async fn get_event(&self, event_id: &str) -> ApiResult<Event> {
+        self.events.get_or_fetch(&event_id.to_string(), || self.api.get_event(event_id)).await
+    }
+
+    pub async fn get_balance(&self, wallet: &str) -> ApiResult<f64> {
+        self.balances.get_or_fetch(&wallet.to_lowercase(), || self.api.get_balance(wallet)).await
+    }
+
+    // Fresh market data that also refreshes the cache, e.g. for marking
+    pub async fn refresh_market(&self, market_id: &str) -> ApiResult<Market> {
+        let market = self.api.get_market(market_id).await?;
+        self.markets.insert(market_id.to_string(), market.clone());
+        Ok(market)
+    }
+
+    pub fn clock_skew_ms(&self) -> i64 {
+        self.api.clock_skew_ms()
+    }
+
+    pub fn stats(&self) -> [(&'static str, CacheStats); 3] {
+        [
+            ("markets", self.markets.stats()),
+            ("events", self.events.stats()),
+            ("balances", self.balances.stats()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[tokio::test]
+    async fn test_concurrent_misses_share_one_fetch() {
+        let cache: Arc<TtlCache<String, u32>> = Arc::new(TtlCache::new(Duration::from_secs(60), 10));
+        let fetches = Arc::new(AtomicUsize::new(0));
+
+        let lookups = (0..5).map(|_| {
+            let (cache, fetches) = (Arc::clone(&cache), Arc::clone(&fetches));
+            tokio::spawn(async move {
+                cache.get_or_fetch(&"m1".to_string(), || async {
+                    fetches.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    Ok::<_, String>(7)
+                }).await
+            })
+        }).collect::<Vec<_>>();
+        for lookup in lookups {
+            assert_eq!(lookup.await.unwrap(), Ok(7));
+        }
+
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.stats(), CacheStats { len: 1, hits: 4, misses: 1 });
+        assert!(cache.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_entries_expire_and_errors_are_not_cached() {
+        let cache: TtlCache<String, u32> = TtlCache::new(Duration::from_millis(20), 10);
+        let key = "m1".to_string();
+
+        assert!(cache.get_or_fetch(&key, || async { Err::<u32, _>("down") }).await.is_err());
+        assert_eq!(cache.get_or_fetch(&key, || async { Ok::<_, &str>(1) }).await, Ok(1));
+        assert_eq!(cache.get_or_fetch(&key, || async { Ok::<_, &str>(2) }).await, Ok(1));
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(cache.get(&key), None);
+        assert_eq!(cache.get_or_fetch(&key, || async { Ok::<_, &str>(3) }).await, Ok(3));
+    }
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let cache: TtlCache<&str, u32> = TtlCache::new(Duration::from_secs(60), 2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        assert_eq!(cache.get(&"a"), Some(1));
+
+        cache.insert("c", 3);
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"a"), Some(1));
+        assert_eq!(cache.get(&"c"), Some(3));
+    }
+}
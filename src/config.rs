@@ -1,5 +1,6 @@
-use crate::types::{Config, SizingMode};
+use crate::types::{Config, FilterRules, OverflowPolicy, SizingMode, WalletBudget};
 use anyhow::{Context, Result};
+use std::collections::HashMap;
 use std::env;
 
 pub fn load_config() -> Result<Config> {
@@ -21,6 +22,57 @@ pub fn load_config() -> Result<Config> {
         _ => SizingMode::Fixed,
     };
     
+    let overflow_policy = match env::var("OVERFLOW_POLICY")
+        .unwrap_or_else(|_| "drop-stale".to_string())
+        .to_lowercase()
+        .as_str()
+    {
+        "block" => OverflowPolicy::Block,
+        "drop-oldest" => OverflowPolicy::DropOldest,
+        "drop-stale" => OverflowPolicy::DropStale,
+        other => anyhow::bail!("Unknown OVERFLOW_POLICY '{}' (block, drop-oldest or drop-stale)", other),
+    };
+    
+    let default_wallet_budget = WalletBudget {
+        bankroll: env::var("WALLET_BANKROLL")
+            .unwrap_or_else(|_| "1000.0".to_string())
+            .parse()?,
+        max_exposure: env::var("WALLET_MAX_EXPOSURE")
+            .unwrap_or_else(|_| "250.0".to_string())
+            .parse()?,
+        max_daily_volume: env::var("WALLET_MAX_DAILY_VOLUME")
+            .unwrap_or_else(|_| "1000.0".to_string())
+            .parse()?,
+    };
+    let wallet_budgets = parse_wallet_budgets(&env::var("WALLET_BUDGETS").unwrap_or_default())?;
+    
+    let filter_rules = FilterRules {
+        min_price: env::var("MIN_PRICE")
+            .unwrap_or_else(|_| "0.05".to_string())
+            .parse()?,
+        max_price: env::var("MAX_PRICE")
+            .unwrap_or_else(|_| "0.95".to_string())
+            .parse()?,
+        min_hours_to_resolution: env::var("MIN_HOURS_TO_RESOLUTION")
+            .unwrap_or_else(|_| "1.0".to_string())
+            .parse()?,
+        max_days_to_resolution: env::var("MAX_DAYS_TO_RESOLUTION")
+            .unwrap_or_else(|_| "365.0".to_string())
+            .parse()?,
+        blocked_categories: env::var("BLOCKED_CATEGORIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect(),
+        deny_keywords: env::var("DENY_KEYWORDS").ok().filter(|s| !s.is_empty()),
+        allow_keywords: env::var("ALLOW_KEYWORDS").ok().filter(|s| !s.is_empty()),
+    };
+    let wallet_filter_rules = parse_filter_overrides(
+        &env::var("WALLET_FILTER_OVERRIDES").unwrap_or_default(),
+        &filter_rules,
+    )?;
+    
     Ok(Config {
         wallets_to_track: wallets,
         your_wallet: env::var("YOUR_WALLET")
@@ -31,9 +83,32 @@ pub fn load_config() -> Result<Config> {
             .unwrap_or_else(|_| "https://api.polymarket.com".to_string()),
         ws_url: env::var("WS_URL")
             .unwrap_or_else(|_| "wss://ws-subscriptions-clob.polymarket.com/ws".to_string()),
+        market_ws_url: env::var("MARKET_WS_URL")
+            .unwrap_or_else(|_| "wss://ws-subscriptions-clob.polymarket.com/ws/market".to_string()),
         rpc_url: env::var("RPC_URL")
             .context("RPC_URL not set (use Alchemy/Infura)")?,
         
+        order_book_mirror: env::var("ORDER_BOOK_MIRROR")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse()?,
+        book_idle_timeout_secs: env::var("BOOK_IDLE_TIMEOUT_SECS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse()?,
+        
+        api_timeout_ms: env::var("API_TIMEOUT_MS")
+            .unwrap_or_else(|_| "10000".to_string())
+            .parse()?,
+        api_connect_timeout_ms: env::var("API_CONNECT_TIMEOUT_MS")
+            .unwrap_or_else(|_| "3000".to_string())
+            .parse()?,
+        http_proxy: env::var("HTTP_PROXY_URL").unwrap_or_default(),
+        api_prewarm_connections: env::var("API_PREWARM_CONNECTIONS")
+            .unwrap_or_else(|_| "4".to_string())
+            .parse()?,
+        rate_limit_headroom: env::var("RATE_LIMIT_HEADROOM")
+            .unwrap_or_else(|_| "0.8".to_string())
+            .parse()?,
+        
         sizing_mode,
         fixed_stake: env::var("FIXED_STAKE")
             .unwrap_or_else(|_| "25.0".to_string())
@@ -51,9 +126,37 @@ pub fn load_config() -> Result<Config> {
         max_exposure_per_event: env::var("MAX_EXPOSURE_PER_EVENT")
             .unwrap_or_else(|_| "500.0".to_string())
             .parse()?,
+        max_open_positions: env::var("MAX_OPEN_POSITIONS")
+            .unwrap_or_else(|_| "20".to_string())
+            .parse()?,
+        max_market_concentration: env::var("MAX_MARKET_CONCENTRATION")
+            .unwrap_or_else(|_| "0.2".to_string())
+            .parse()?,
+        max_event_concentration: env::var("MAX_EVENT_CONCENTRATION")
+            .unwrap_or_else(|_| "0.35".to_string())
+            .parse()?,
+        max_gross_exposure_pct: env::var("MAX_GROSS_EXPOSURE_PCT")
+            .unwrap_or_else(|_| "0.8".to_string())
+            .parse()?,
         max_daily_volume: env::var("MAX_DAILY_VOLUME")
             .unwrap_or_else(|_| "2000.0".to_string())
             .parse()?,
+        max_hourly_volume: env::var("MAX_HOURLY_VOLUME")
+            .unwrap_or_else(|_| "500.0".to_string())
+            .parse()?,
+        max_trades_per_minute: env::var("MAX_TRADES_PER_MINUTE")
+            .unwrap_or_else(|_| "10".to_string())
+            .parse()?,
+        max_wallet_trades_per_minute: env::var("MAX_WALLET_TRADES_PER_MINUTE")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse()?,
+        market_cooldown_secs: env::var("MARKET_COOLDOWN_SECS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse()?,
+        default_wallet_budget,
+        wallet_budgets,
+        filter_rules,
+        wallet_filter_rules,
         min_liquidity: env::var("MIN_LIQUIDITY")
             .unwrap_or_else(|_| "1000.0".to_string())
             .parse()?,
@@ -63,16 +166,192 @@ pub fn load_config() -> Result<Config> {
         cb_min_depth_usd: env::var("CB_MIN_DEPTH_USD")
             .unwrap_or_else(|_| "100.0".to_string())
             .parse()?,
+        cb_cooldown_secs: env::var("CB_COOLDOWN_SECS")
+            .unwrap_or_else(|_| "300".to_string())
+            .parse()?,
+        cb_probe_trades: env::var("CB_PROBE_TRADES")
+            .unwrap_or_else(|_| "1".to_string())
+            .parse()?,
+        cb_error_window_secs: env::var("CB_ERROR_WINDOW_SECS")
+            .unwrap_or_else(|_| "300".to_string())
+            .parse()?,
+        cb_error_rate_threshold: env::var("CB_ERROR_RATE_THRESHOLD")
+            .unwrap_or_else(|_| "0.5".to_string())
+            .parse()?,
+        cb_error_rate_min_samples: env::var("CB_ERROR_RATE_MIN_SAMPLES")
+            .unwrap_or_else(|_| "10".to_string())
+            .parse()?,
+        cb_max_daily_loss: env::var("CB_MAX_DAILY_LOSS")
+            .unwrap_or_else(|_| "200.0".to_string())
+            .parse()?,
+        cb_max_drawdown_pct: env::var("CB_MAX_DRAWDOWN_PCT")
+            .unwrap_or_else(|_| "0.25".to_string())
+            .parse()?,
+        cb_latency_spike_ms: env::var("CB_LATENCY_SPIKE_MS")
+            .unwrap_or_else(|_| "10000".to_string())
+            .parse()?,
+        cb_max_slippage_bps: env::var("CB_MAX_SLIPPAGE_BPS")
+            .unwrap_or_else(|_| "500.0".to_string())
+            .parse()?,
+        max_daily_loss: env::var("MAX_DAILY_LOSS")
+            .unwrap_or_else(|_| "300.0".to_string())
+            .parse()?,
+        max_drawdown_pct: env::var("MAX_DRAWDOWN_PCT")
+            .unwrap_or_else(|_| "0.3".to_string())
+            .parse()?,
+        flatten_on_kill: env::var("FLATTEN_ON_KILL")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()?,
+        mark_interval_secs: env::var("MARK_INTERVAL_SECS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse()?,
+        daily_reset_time: env::var("DAILY_RESET_TIME")
+            .unwrap_or_else(|_| "00:00".to_string()),
+        daily_reset_timezone: env::var("DAILY_RESET_TIMEZONE")
+            .unwrap_or_else(|_| "UTC".to_string()),
+        reset_state_path: env::var("RESET_STATE_PATH")
+            .unwrap_or_else(|_| "data/last_reset".to_string()),
+        
+        consensus_enabled: env::var("CONSENSUS_ENABLED")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()?,
+        consensus_threshold: env::var("CONSENSUS_THRESHOLD")
+            .unwrap_or_else(|_| "2.0".to_string())
+            .parse()?,
+        consensus_window_secs: env::var("CONSENSUS_WINDOW_SECS")
+            .unwrap_or_else(|_| "300".to_string())
+            .parse()?,
+        consensus_max_multiplier: env::var("CONSENSUS_MAX_MULTIPLIER")
+            .unwrap_or_else(|_| "2.0".to_string())
+            .parse()?,
+        wallet_weights: parse_wallet_weights(&env::var("WALLET_WEIGHTS").unwrap_or_default())?,
+        inverted_wallets: env::var("INVERT_WALLETS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty())
+            .collect(),
+        
+        max_signal_age_secs: env::var("MAX_SIGNAL_AGE_SECS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse()?,
+        max_price_drift: env::var("MAX_PRICE_DRIFT")
+            .unwrap_or_else(|_| "0.02".to_string())
+            .parse()?,
+        fill_aggregation_ms: env::var("FILL_AGGREGATION_MS")
+            .unwrap_or_else(|_| "500".to_string())
+            .parse()?,
+        
+        score_sizing: env::var("SCORE_SIZING")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()?,
+        auto_disable_wallets: env::var("AUTO_DISABLE_WALLETS")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()?,
+        score_interval_secs: env::var("SCORE_INTERVAL_SECS")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse()?,
+        score_lookback_days: env::var("SCORE_LOOKBACK_DAYS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse()?,
+        min_scored_positions: env::var("MIN_SCORED_POSITIONS")
+            .unwrap_or_else(|_| "10".to_string())
+            .parse()?,
+        min_wallet_roi: env::var("MIN_WALLET_ROI")
+            .unwrap_or_else(|_| "-0.1".to_string())
+            .parse()?,
+        
+        market_cache_ttl_ms: env::var("MARKET_CACHE_TTL_MS")
+            .unwrap_or_else(|_| "2000".to_string())
+            .parse()?,
+        event_cache_ttl_secs: env::var("EVENT_CACHE_TTL_SECS")
+            .unwrap_or_else(|_| "300".to_string())
+            .parse()?,
+        balance_cache_ttl_secs: env::var("BALANCE_CACHE_TTL_SECS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse()?,
+        cache_capacity: env::var("CACHE_CAPACITY")
+            .unwrap_or_else(|_| "1000".to_string())
+            .parse()?,
+        balance_reconcile_secs: env::var("BALANCE_RECONCILE_SECS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse()?,
         
+        pipeline_workers: env::var("PIPELINE_WORKERS")
+            .unwrap_or_else(|_| "4".to_string())
+            .parse()?,
+        signal_queue_capacity: env::var("SIGNAL_QUEUE_CAPACITY")
+            .unwrap_or_else(|_| "1000".to_string())
+            .parse()?,
+        overflow_policy,
         retry_attempts: env::var("RETRY_ATTEMPTS")
             .unwrap_or_else(|_| "4".to_string())
             .parse()?,
         retry_delay_ms: env::var("RETRY_DELAY_MS")
             .unwrap_or_else(|_| "500".to_string())
             .parse()?,
+        order_ttl_secs: env::var("ORDER_TTL_SECS")
+            .unwrap_or_else(|_| "300".to_string())
+            .parse()?,
+        order_poll_interval_ms: env::var("ORDER_POLL_INTERVAL_MS")
+            .unwrap_or_else(|_| "2000".to_string())
+            .parse()?,
     })
 }
 
+// "0xabc:1500:400:800,0xdef:500:100:300" -> wallet:bankroll:max_exposure:max_daily_volume
+fn parse_wallet_budgets(raw: &str) -> Result<HashMap<String, WalletBudget>> {
+    let mut budgets = HashMap::new();
+    for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let parts: Vec<&str> = entry.split(':').map(str::trim).collect();
+        let [wallet, bankroll, max_exposure, max_daily_volume] = parts[..] else {
+            anyhow::bail!("WALLET_BUDGETS entry '{}' must be wallet:bankroll:max_exposure:max_daily_volume", entry);
+        };
+        budgets.insert(wallet.to_lowercase(), WalletBudget {
+            bankroll: bankroll.parse().with_context(|| format!("Bad bankroll in '{}'", entry))?,
+            max_exposure: max_exposure.parse().with_context(|| format!("Bad max exposure in '{}'", entry))?,
+            max_daily_volume: max_daily_volume.parse().with_context(|| format!("Bad max daily volume in '{}'", entry))?,
+        });
+    }
+    Ok(budgets)
+}
+
+// "0xabc:2,0xdef:0.5" -> wallet:weight
+fn parse_wallet_weights(raw: &str) -> Result<HashMap<String, f64>> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+        .map(|entry| {
+            let (wallet, weight) = entry.split_once(':')
+                .with_context(|| format!("WALLET_WEIGHTS entry '{}' must be wallet:weight", entry))?;
+            let weight = weight.trim().parse()
+                .with_context(|| format!("Bad weight in '{}'", entry))?;
+            Ok((wallet.trim().to_lowercase(), weight))
+        })
+        .collect()
+}
+
+// JSON object of wallet -> partial rules, e.g. {"0xabc": {"max_price": 0.99}}.
+// Fields left out inherit from the default rules.
+fn parse_filter_overrides(raw: &str, defaults: &FilterRules) -> Result<HashMap<String, FilterRules>> {
+    if raw.trim().is_empty() {
+        return Ok(HashMap::new());
+    }
+    
+    let overrides: HashMap<String, serde_json::Map<String, serde_json::Value>> = serde_json::from_str(raw)
+        .context("WALLET_FILTER_OVERRIDES must be a JSON object of wallet -> rules")?;
+    
+    overrides.into_iter()
+        .map(|(wallet, fields)| {
+            let mut rules = serde_json::to_value(defaults)?;
+            rules.as_object_mut().unwrap().extend(fields);
+            let rules = serde_json::from_value(rules)
+                .with_context(|| format!("Bad filter override for {}", wallet))?;
+            Ok((wallet.to_lowercase(), rules))
+        })
+        .collect()
+}
+
 pub fn validate_config(config: &Config) -> Result<()> {
     if config.wallets_to_track.is_empty() {
         anyhow::bail!("No wallets to track configured");
@@ -94,6 +373,154 @@ pub fn validate_config(config: &Config) -> Result<()> {
         anyhow::bail!("MAX_STAKE must be >= MIN_STAKE");
     }
     
+    for (name, value) in [
+        ("MAX_MARKET_CONCENTRATION", config.max_market_concentration),
+        ("MAX_EVENT_CONCENTRATION", config.max_event_concentration),
+        ("MAX_GROSS_EXPOSURE_PCT", config.max_gross_exposure_pct),
+    ] {
+        if !(0.0..=1.0).contains(&value) {
+            anyhow::bail!("{} must be between 0 and 1", name);
+        }
+    }
+    
+    if !(0.0..=1.0).contains(&config.cb_error_rate_threshold) {
+        anyhow::bail!("CB_ERROR_RATE_THRESHOLD must be between 0 and 1");
+    }
+    
+    if !(0.0..=1.0).contains(&config.cb_max_drawdown_pct) {
+        anyhow::bail!("CB_MAX_DRAWDOWN_PCT must be between 0 and 1");
+    }
+    
+    if config.max_daily_loss <= 0.0 {
+        anyhow::bail!("MAX_DAILY_LOSS must be > 0");
+    }
+    
+    if !(0.0..=1.0).contains(&config.max_drawdown_pct) {
+        anyhow::bail!("MAX_DRAWDOWN_PCT must be between 0 and 1");
+    }
+    
+    if config.mark_interval_secs == 0 {
+        anyhow::bail!("MARK_INTERVAL_SECS must be > 0");
+    }
+    
+    if chrono::NaiveTime::parse_from_str(&config.daily_reset_time, "%H:%M").is_err() {
+        anyhow::bail!("DAILY_RESET_TIME must be HH:MM");
+    }
+    
+    if config.daily_reset_timezone.parse::<chrono_tz::Tz>().is_err() {
+        anyhow::bail!("Unknown DAILY_RESET_TIMEZONE: {}", config.daily_reset_timezone);
+    }
+    
+    // The CLOB rejects GTD orders expiring less than a minute out
+    if config.order_ttl_secs < 60 {
+        anyhow::bail!("ORDER_TTL_SECS must be >= 60");
+    }
+    
+    if config.order_poll_interval_ms == 0 {
+        anyhow::bail!("ORDER_POLL_INTERVAL_MS must be > 0");
+    }
+    
+    for wallet in config.wallet_budgets.keys() {
+        if !config.wallets_to_track.iter().any(|w| w.to_lowercase() == *wallet) {
+            anyhow::bail!("WALLET_BUDGETS has an entry for untracked wallet {}", wallet);
+        }
+    }
+    
+    for wallet in &config.inverted_wallets {
+        if !config.wallets_to_track.iter().any(|w| w.to_lowercase() == *wallet) {
+            anyhow::bail!("INVERT_WALLETS has untracked wallet {}", wallet);
+        }
+    }
+    
+    for rules in std::iter::once(&config.filter_rules).chain(config.wallet_filter_rules.values()) {
+        if rules.min_price > rules.max_price {
+            anyhow::bail!("MIN_PRICE must be <= MAX_PRICE");
+        }
+    }
+    
+    if config.consensus_threshold <= 0.0 {
+        anyhow::bail!("CONSENSUS_THRESHOLD must be > 0");
+    }
+    
+    if config.consensus_max_multiplier < 1.0 {
+        anyhow::bail!("CONSENSUS_MAX_MULTIPLIER must be >= 1");
+    }
+    
+    if config.api_timeout_ms == 0 || config.api_connect_timeout_ms == 0 {
+        anyhow::bail!("API_TIMEOUT_MS and API_CONNECT_TIMEOUT_MS must be > 0");
+    }
+    
+    if config.book_idle_timeout_secs == 0 {
+        anyhow::bail!("BOOK_IDLE_TIMEOUT_SECS must be > 0");
+    }
+    
+    if !(config.rate_limit_headroom > 0.0 && config.rate_limit_headroom <= 1.0) {
+        anyhow::bail!("RATE_LIMIT_HEADROOM must be in (0, 1]");
+    }
+    
+    if !config.http_proxy.is_empty() {
+        reqwest::Proxy::all(&config.http_proxy)
+            .map_err(|e| anyhow::anyhow!("Invalid HTTP_PROXY_URL: {}", e))?;
+    }
+    
+    if config.balance_reconcile_secs == 0 {
+        anyhow::bail!("BALANCE_RECONCILE_SECS must be > 0");
+    }
+    
+    if config.signal_queue_capacity == 0 {
+        anyhow::bail!("SIGNAL_QUEUE_CAPACITY must be > 0");
+    }
+    
+    if config.pipeline_workers == 0 {
+        anyhow::bail!("PIPELINE_WORKERS must be > 0");
+    }
+    
+    if config.score_interval_secs == 0 {
+        anyhow::bail!("SCORE_INTERVAL_SECS must be > 0");
+    }
+    
+    // Compiles the keyword regexes
+    crate::filters::MarketFilters::new(config)?;
+    
     tracing::info!("Config validation passed");
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_wallet_budgets() {
+        let budgets = parse_wallet_budgets("0xABC:1500:400:800, 0xdef:500:100:300").unwrap();
+        assert_eq!(budgets.len(), 2);
+        assert_eq!(budgets["0xabc"], WalletBudget { bankroll: 1500.0, max_exposure: 400.0, max_daily_volume: 800.0 });
+        
+        assert!(parse_wallet_budgets("").unwrap().is_empty());
+        assert!(parse_wallet_budgets("0xabc:1500:400").is_err());
+        assert!(parse_wallet_budgets("0xabc:lots:400:800").is_err());
+    }
+    
+    #[test]
+    fn test_parse_wallet_weights() {
+        let weights = parse_wallet_weights("0xABC:2, 0xdef:0.5").unwrap();
+        assert_eq!(weights["0xabc"], 2.0);
+        assert_eq!(weights["0xdef"], 0.5);
+        assert!(parse_wallet_weights("0xabc").is_err());
+    }
+    
+    #[test]
+    fn test_filter_overrides_inherit_defaults() {
+        let defaults = FilterRules { blocked_categories: vec!["sports".to_string()], ..Default::default() };
+        let overrides = parse_filter_overrides(r#"{"0xABC": {"max_price": 0.99, "deny_keywords": "rug"}}"#, &defaults).unwrap();
+        
+        let rules = &overrides["0xabc"];
+        assert_eq!(rules.max_price, 0.99);
+        assert_eq!(rules.deny_keywords.as_deref(), Some("rug"));
+        assert_eq!(rules.min_price, 0.05);
+        assert_eq!(rules.blocked_categories, vec!["sports"]);
+        
+        assert!(parse_filter_overrides("", &defaults).unwrap().is_empty());
+        assert!(parse_filter_overrides(r#"{"0xabc": {"max_price": "high"}}"#, &defaults).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use crate::types::{Config, Outcome, Trade, TradeSide};
+use std::collections::HashMap;
+
+type Key = (String, Outcome);  // Market and outcome being bought
+
+#[derive(Debug, Clone)]
+pub struct ConsensusSignal {
+    pub trade: Trade,  // The trade that completed the consensus
+    pub wallets: Vec<String>,
+    pub agreement: f64,  // Summed weight of the agreeing wallets
+    pub multiplier: f64,  // Size scaling from the agreement
+}
+
+// Buffers whale buys per market outcome and only lets one through once
+// enough distinct wallets, by weight, have bought the same outcome within
+// the window. Sells pass straight through so we can always follow an exit.
+pub struct ConsensusAggregator {
+    config: Config,
+    votes: HashMap<Key, HashMap<String, Trade>>,  // Latest buy per wallet
+    fired: HashMap<Key, i64>,  // When consensus last fired, to fire once per window
+}
+
+impl ConsensusAggregator {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            votes: HashMap::new(),
+            fired: HashMap::new(),
+        }
+    }
+
+    pub fn observe(&mut self, trade: Trade) -> Option<ConsensusSignal> {
+        let now = trade.timestamp;
+        self.expire(now);
+
+        if trade.side == TradeSide::SELL {
+            // Selling withdraws the wallet's vote for that outcome
+            let key = (trade.market_id.clone(), trade.outcome);
+            if let Some(votes) = self.votes.get_mut(&key) {
+                votes.remove(&trade.wallet.to_lowercase());
+            }
+            return Some(ConsensusSignal {
+                wallets: vec![trade.wallet.clone()],
+                agreement: self.config.wallet_weight(&trade.wallet),
+                multiplier: 1.0,
+                trade,
+            });
+        }
+
+        // Buying one outcome is a vote against the other
+        let opposite = (trade.market_id.clone(), trade.outcome.opposite());
+        if let Some(votes) = self.votes.get_mut(&opposite) {
+            votes.remove(&trade.wallet.to_lowercase());
+        }
+
+        let key = (trade.market_id.clone(), trade.outcome);
+        if self.fired.contains_key(&key) {
+            tracing::debug!("Consensus on {} {:?} already acted on this window", trade.market_id, trade.outcome);
+            return None;
+        }
+
+        let votes = self.votes.entry(key.clone()).or_default();
+        votes.insert(trade.wallet.to_lowercase(), trade.clone());
+
+        let agreement: f64 = votes.keys().map(|w| self.config.wallet_weight(w)).sum();
+        if agreement < self.config.consensus_threshold {
+            tracing::info!("Consensus on {} {:?}: {:.2}/{:.2}, waiting",
+                trade.market_id, trade.outcome, agreement, self.config.consensus_threshold);
+            return None;
+        }
+
+        let mut wallets: Vec<String> = votes.keys().cloned().collect();
+        wallets.sort();
+        self.votes.remove(&key);
+        self.fired.insert(key, now);
+
+        let multiplier = (agreement / self.config.consensus_threshold)
+            .min(self.config.consensus_max_multiplier);
+        tracing::info!("Consensus reached on {} {:?}: {} wallets, weight {:.2}, size x{:.2}",
+            trade.market_id, trade.outcome, wallets.len(), agreement, multiplier);
+
+        Some(ConsensusSignal { trade, wallets, agreement, multiplier })
+    }
+
+    fn expire(&mut self, now: i64) {
+        let window = self.config.consensus_window_secs as i64;
+        for votes in self.votes.values_mut() {
+            votes.retain(|_, t| now - t.timestamp < window);
+        }
+        self.votes.retain(|_, votes| !votes.is_empty());
+        self.fired.retain(|_, at| now - *at < window);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::fixtures;
+
+    fn buy(wallet: &str, outcome: Outcome, timestamp: i64) -> Trade {
+        Trade {
+            wallet: wallet.to_string(),
+            outcome,
+            timestamp,
+            ..fixtures::trade("market1")
+        }
+    }
+
+    fn aggregator() -> ConsensusAggregator {
+        let mut config = Config {
+            consensus_threshold: 2.0,
+            consensus_window_secs: 300,
+            consensus_max_multiplier: 2.0,
+            ..Default::default()
+        };
+        config.wallet_weights.insert("0xstar".to_string(), 2.0);
+        ConsensusAggregator::new(config)
+    }
+
+    #[test]
+    fn test_fires_once_when_enough_wallets_agree() {
+        let mut agg = aggregator();
+        assert!(agg.observe(buy("0xa", Outcome::Yes, 0)).is_none());
+        // The same wallet again doesn't count twice
+        assert!(agg.observe(buy("0xa", Outcome::Yes, 10)).is_none());
+
+        let signal = agg.observe(buy("0xb", Outcome::Yes, 20)).unwrap();
+        assert_eq!(signal.wallets, vec!["0xa", "0xb"]);
+        assert_eq!(signal.multiplier, 1.0);
+
+        // A third whale piling in doesn't trigger another copy this window
+        assert!(agg.observe(buy("0xc", Outcome::Yes, 30)).is_none());
+    }
+
+    #[test]
+    fn test_votes_expire_and_can_be_withdrawn() {
+        let mut agg = aggregator();
+        agg.observe(buy("0xa", Outcome::Yes, 0));
+        assert!(agg.observe(buy("0xb", Outcome::Yes, 400)).is_none());
+
+        // 0xb changes its mind before 0xc agrees
+        agg.observe(buy("0xb", Outcome::No, 410));
+        assert!(agg.observe(buy("0xc", Outcome::Yes, 420)).is_none());
+
+        let mut sell = buy("0xc", Outcome::Yes, 430);
+        sell.side = TradeSide::SELL;
+        assert!(agg.observe(sell).is_some());
+        assert!(agg.observe(buy("0xd", Outcome::Yes, 440)).is_none());
+    }
+
+    #[test]
+    fn test_weighted_agreement_scales_size() {
+        let mut agg = aggregator();
+        assert!(agg.observe(buy("0xa", Outcome::No, 0)).is_none());
+        let signal = agg.observe(buy("0xSTAR", Outcome::No, 5)).unwrap();
+        assert_eq!(signal.agreement, 3.0);
+        assert_eq!(signal.multiplier, 1.5);
+
+        // A heavy enough wallet can carry consensus alone, capped at the max multiplier
+        let mut agg = ConsensusAggregator::new(Config {
+            consensus_threshold: 1.0,
+            consensus_max_multiplier: 1.5,
+            ..agg.config.clone()
+        });
+        assert_eq!(agg.observe(buy("0xstar", Outcome::Yes, 0)).unwrap().multiplier, 1.5);
+    }
+}
@@ -0,0 +1,210 @@
+use crate::analytics::{WhaleScorer, WhaleStats};
+use crate::api::MarketHistory;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct DiscoveryParams {
+    pub markets: usize,  // Busiest active markets to scan
+    pub fill_window_secs: i64,  // How far back to look for large fills
+    pub min_fill_usd: f64,
+    pub max_scored: usize,  // Wallets to score, by volume in large fills
+    pub score_window_secs: i64,  // Track record window
+    pub min_positions: usize,
+    pub min_roi: f64,
+    pub max_candidates: usize,
+}
+
+impl Default for DiscoveryParams {
+    fn default() -> Self {
+        Self {
+            markets: 25,
+            fill_window_secs: 86400,
+            min_fill_usd: 1000.0,
+            max_scored: 50,
+            score_window_secs: 30 * 86400,
+            min_positions: 10,
+            min_roi: 0.05,
+            max_candidates: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub stats: WhaleStats,
+    pub large_fill_volume: f64,  // USD in large fills during the scan window
+}
+
+// Finds wallets worth tracking: whoever is placing large fills in the busiest
+// markets right now, ranked by what they have actually realized recently.
+pub async fn discover(
+    source: &dyn MarketHistory,
+    params: &DiscoveryParams,
+    now: i64,
+    tracked: &[String],
+) -> Result<Vec<Candidate>> {
+    let markets = source.get_active_markets(params.markets).await
+        .context("Failed to fetch active markets")?;
+
+    let mut volume: HashMap<String, f64> = HashMap::new();
+    for market in &markets {
+        let fills = match source.get_market_trades(&market.id, now - params.fill_window_secs).await {
+            Ok(f) => f,
+            Err(e) => {
+                tracing::warn!("Skipping market {}: {}", market.id, e);
+                continue;
+            }
+        };
+        for fill in fills {
+            let usd = fill.shares * fill.price;
+            if usd >= params.min_fill_usd {
+                *volume.entry(fill.wallet.to_lowercase()).or_default() += usd;
+            }
+        }
+    }
+
+    let mut active: Vec<(String, f64)> = volume.into_iter()
+        .filter(|(wallet, _)| !tracked.iter().any(|t| t.eq_ignore_ascii_case(wallet)))
+        .collect();
+    active.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    active.truncate(params.max_scored);
+    tracing::info!("Scanned {} markets, scoring {} wallets with large fills", markets.len(), active.len());
+
+    let mut scorer = WhaleScorer::new(source);
+    let mut candidates = Vec::new();
+    for (wallet, large_fill_volume) in active {
+        let stats = match scorer.score_wallet(&wallet, now - params.score_window_secs).await {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::warn!("{:#}", e);
+                continue;
+            }
+        };
+        if stats.closed_positions >= params.min_positions && stats.roi >= params.min_roi {
+            candidates.push(Candidate { stats, large_fill_volume });
+        }
+    }
+
+    candidates.sort_by(|a, b| b.stats.realized_pnl.total_cmp(&a.stats.realized_pnl)
+        .then_with(|| a.stats.wallet.cmp(&b.stats.wallet)));
+    candidates.truncate(params.max_candidates);
+    Ok(candidates)
+}
+
+// Candidates as .env lines: a commented summary of each, then the tracked
+// list with them appended
+pub fn render_env(candidates: &[Candidate], tracked: &[String]) -> String {
+    let mut out = String::from("# Whale discovery candidates\n");
+    for c in candidates {
+        let best = c.stats.categories.first()
+            .map(|cat| format!(", best in {}", cat.category))
+            .unwrap_or_default();
+        out.push_str(&format!("# {}: ROI {:+.1}%, win {:.0}%, {} closed, PnL ${:+.2}, ${:.0} in large fills{}\n",
+            c.stats.wallet, c.stats.roi * 100.0, c.stats.win_rate * 100.0, c.stats.closed_positions,
+            c.stats.realized_pnl, c.large_fill_volume, best));
+    }
+    let wallets: Vec<&str> = tracked.iter().map(String::as_str)
+        .chain(candidates.iter().map(|c| c.stats.wallet.as_str()))
+        .collect();
+    out.push_str(&format!("WALLETS_TO_TRACK={}\n", wallets.join(",")));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::RecordedHistory;
+    use serde_json::{json, Value};
+
+    const NOW: i64 = 1_700_000_000;
+    const DAY: i64 = 86400;
+
+    fn market(id: &str, volume_24h: f64, resolved: Option<&str>) -> Value {
+        json!({
+            "id": id,
+            "event_id": format!("event-{}", id),
+            "question": format!("Will {} happen?", id),
+            "tokens": [{"token_id": format!("{}-yes", id), "outcome": "Yes"}, {"token_id": format!("{}-no", id), "outcome": "No"}],
+            "yes_price": 0.5,
+            "no_price": 0.5,
+            "liquidity": 10000.0,
+            "volume_24h": volume_24h,
+            "end_date": "2023-11-10T00:00:00Z",
+            "category": "Politics",
+            "resolved_outcome": resolved,
+        })
+    }
+
+    fn fill(wallet: &str, market_id: &str, outcome: &str, shares: f64, price: f64, timestamp: i64) -> Value {
+        json!({
+            "wallet": wallet,
+            "event_id": format!("event-{}", market_id),
+            "market_id": market_id,
+            "outcome": outcome,
+            "side": "BUY",
+            "shares": shares,
+            "price": price,
+            "timestamp": timestamp,
+        })
+    }
+
+    fn recording() -> RecordedHistory {
+        RecordedHistory::from_recording(&json!({
+            "markets": [
+                market("hot", 50_000.0, None),
+                market("quiet", 100.0, None),
+                market("old1", 0.0, Some("Yes")),
+                market("old2", 0.0, Some("No")),
+            ],
+            "trades": [
+                // Large fills in the busiest market today
+                fill("0xSharp", "hot", "Yes", 5000.0, 0.5, NOW - 3600),
+                fill("0xfish", "hot", "No", 4000.0, 0.5, NOW - 3600),
+                fill("0xtracked", "hot", "Yes", 4000.0, 0.5, NOW - 3600),
+                fill("0xminnow", "hot", "Yes", 100.0, 0.5, NOW - 3600),
+                // Only in a market outside the scan
+                fill("0xquiet", "quiet", "Yes", 5000.0, 0.5, NOW - 3600),
+                // Track records
+                fill("0xsharp", "old1", "Yes", 1000.0, 0.4, NOW - 10 * DAY),
+                fill("0xsharp", "old2", "No", 1000.0, 0.3, NOW - 10 * DAY),
+                fill("0xfish", "old1", "No", 1000.0, 0.6, NOW - 10 * DAY),
+                fill("0xfish", "old2", "Yes", 1000.0, 0.7, NOW - 10 * DAY),
+                fill("0xminnow", "old1", "Yes", 1000.0, 0.1, NOW - 10 * DAY),
+                fill("0xminnow", "old2", "No", 1000.0, 0.1, NOW - 10 * DAY),
+                fill("0xquiet", "old1", "Yes", 1000.0, 0.1, NOW - 10 * DAY),
+                fill("0xquiet", "old2", "No", 1000.0, 0.1, NOW - 10 * DAY),
+            ],
+        })).unwrap()
+    }
+
+    fn params() -> DiscoveryParams {
+        DiscoveryParams { markets: 1, min_positions: 2, min_roi: 0.0, ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn test_discovers_profitable_large_traders() {
+        let history = recording();
+        let tracked = vec!["0xTracked".to_string()];
+        let candidates = discover(&history, &params(), NOW, &tracked).await.unwrap();
+
+        // The losing whale and the small trader don't make it, nor does anyone already tracked
+        assert_eq!(candidates.len(), 1);
+        let sharp = &candidates[0];
+        assert_eq!(sharp.stats.wallet, "0xsharp");
+        assert_eq!(sharp.stats.closed_positions, 2);
+        assert!((sharp.stats.realized_pnl - 1300.0).abs() < 1e-9);
+        assert!((sharp.large_fill_volume - 2500.0).abs() < 1e-9);
+
+        let env = render_env(&candidates, &tracked);
+        assert!(env.contains("# 0xsharp: ROI +185.7%, win 100%, 2 closed"), "{}", env);
+        assert!(env.ends_with("WALLETS_TO_TRACK=0xTracked,0xsharp\n"), "{}", env);
+    }
+
+    #[tokio::test]
+    async fn test_track_record_outside_window_is_ignored() {
+        let history = recording();
+        let params = DiscoveryParams { score_window_secs: 5 * DAY, ..params() };
+        assert!(discover(&history, &params, NOW, &[]).await.unwrap().is_empty());
+    }
+}
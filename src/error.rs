@@ -0,0 +1,116 @@
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ApiError {
+    #[error("HTTP {status}: {body}")]
+    Http { status: u16, body: String },
+
+    #[error("rate limited (retry after {retry_after:?})")]
+    RateLimited { retry_after: Option<Duration> },
+
+    #[error("authentication failed ({status}): {body}")]
+    Auth { status: u16, body: String },
+
+    #[error("validation failed: {0}")]
+    Validation(String),
+
+    #[error("failed to decode response: {0}")]
+    Decode(String),
+
+    #[error("network error: {0}")]
+    Network(String),
+}
+
+impl ApiError {
+    pub fn from_status(status: u16, retry_after: Option<&str>, body: String) -> Self {
+        match status {
+            401 | 403 => ApiError::Auth { status, body },
+            429 => ApiError::RateLimited {
+                retry_after: retry_after.and_then(parse_retry_after),
+            },
+            400 | 422 => ApiError::Validation(body),
+            _ => ApiError::Http { status, body },
+        }
+    }
+
+    pub fn missing_field(field: &str) -> Self {
+        ApiError::Decode(format!("missing or invalid field `{}`", field))
+    }
+
+    // Only transient failures are worth another attempt. Auth, validation and
+    // decode errors will fail the same way every time.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ApiError::RateLimited { .. } | ApiError::Network(_) => true,
+            ApiError::Http { status, .. } => *status >= 500 || *status == 408,
+            ApiError::Auth { .. } | ApiError::Validation(_) | ApiError::Decode(_) => false,
+        }
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            ApiError::RateLimited { retry_after } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            ApiError::Decode(e.to_string())
+        } else if let Some(status) = e.status() {
+            ApiError::from_status(status.as_u16(), None, e.to_string())
+        } else {
+            ApiError::Network(e.to_string())
+        }
+    }
+}
+
+// Longest we'll honour; a bogus header shouldn't park us for days
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    // Polymarket sends delta-seconds; HTTP dates are not worth supporting here
+    value.trim().parse::<f64>()
+        .ok()
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(|secs| Duration::try_from_secs_f64(secs).unwrap_or(MAX_RETRY_AFTER).min(MAX_RETRY_AFTER))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_classification() {
+        assert!(matches!(ApiError::from_status(401, None, String::new()), ApiError::Auth { .. }));
+        assert!(matches!(ApiError::from_status(403, None, String::new()), ApiError::Auth { .. }));
+        assert!(matches!(ApiError::from_status(422, None, String::new()), ApiError::Validation(_)));
+        assert!(matches!(ApiError::from_status(503, None, String::new()), ApiError::Http { status: 503, .. }));
+
+        let err = ApiError::from_status(429, Some("2"), String::new());
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_malformed_retry_after() {
+        assert_eq!(parse_retry_after("1e300"), Some(MAX_RETRY_AFTER));
+        assert_eq!(parse_retry_after("-1"), None);
+        assert_eq!(parse_retry_after("NaN"), None);
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn test_retry_classification() {
+        assert!(ApiError::from_status(429, None, String::new()).is_retryable());
+        assert!(ApiError::from_status(502, None, String::new()).is_retryable());
+        assert!(ApiError::Network("reset".to_string()).is_retryable());
+
+        assert!(!ApiError::from_status(401, None, String::new()).is_retryable());
+        assert!(!ApiError::from_status(400, None, String::new()).is_retryable());
+        assert!(!ApiError::from_status(404, None, String::new()).is_retryable());
+        assert!(!ApiError::missing_field("yes_price").is_retryable());
+    }
+}
@@ -1,43 +1,82 @@
-use crate::api::PolymarketApi;
-use crate::types::{Config, Trade, TradeSide, OrderRequest, OrderType, OrderResponse};
+use crate::api::Exchange;
+use crate::book::OrderBooks;
+use crate::orders::OrderManager;
+use crate::types::{Config, Market, Outcome, Trade, TradeSide, OrderRequest, OrderType, OrderResponse, OrderStatus};
 use anyhow::{Context, Result};
+use rand::Rng;
+use std::sync::Arc;
 use std::time::Duration;
 
 pub struct TradeExecutor {
-    api: PolymarketApi,
+    api: Arc<dyn Exchange>,
+    orders: Arc<OrderManager>,
+    books: Option<Arc<OrderBooks>>,
     config: Config,
 }
 
 impl TradeExecutor {
-    pub fn new(api: PolymarketApi, config: Config) -> Self {
-        Self { api, config }
+    pub fn new(api: impl Exchange + 'static, config: Config) -> Self {
+        let api: Arc<dyn Exchange> = Arc::new(api);
+        let orders = Arc::new(OrderManager::new(Arc::clone(&api), config.clone()));
+        Self { api, orders, books: None, config }
     }
-    
-    pub async fn execute_trade(&self, trade: &Trade, shares: f64) -> Result<OrderResponse> {
+
+    // Price lookups read these books when they're in sync, REST otherwise
+    pub fn with_order_books(mut self, books: Arc<OrderBooks>) -> Self {
+        self.books = Some(books);
+        self
+    }
+
+    pub fn orders(&self) -> Arc<OrderManager> {
+        Arc::clone(&self.orders)
+    }
+
+    pub fn order_books(&self) -> Option<&Arc<OrderBooks>> {
+        self.books.as_ref()
+    }
+
+    pub async fn execute_trade(&self, trade: &Trade, market: &Market, shares: f64) -> Result<OrderResponse> {
+        let token_id = resolve_token(trade, market)?;
+
         let order_type = match trade.side {
             TradeSide::BUY => OrderType::FAK,  // Fill-And-Kill for buys
             TradeSide::SELL => OrderType::GTD,  // Good-Till-Date for sells
         };
-        
+
+        let expiration = match order_type {
+            OrderType::GTD => Some(chrono::Utc::now().timestamp() + self.config.order_ttl_secs as i64),
+            _ => None,
+        };
+
         let order = OrderRequest {
+            client_order_id: new_client_order_id(),
             market_id: trade.market_id.clone(),
+            token_id,
+            outcome: trade.outcome,
             side: trade.side.clone(),
             shares,
             price: Some(trade.price),
             order_type,
+            expiration,
+            neg_risk: market.neg_risk,
         };
-        
-        let result = self.execute_with_retry(order).await;
-        
+
+        let result = self.execute_with_retry(trade, order.clone()).await;
+
         match &result {
             Ok(resp) => {
+                if order.order_type.rests_on_book() && resp.status != OrderStatus::Filled {
+                    self.orders.track(trade, &order, resp);
+                }
+
                 tracing::info!(
-                    "Trade executed: {} {:.2} shares @ ${:.4} (order_id: {})",
+                    "Trade executed: {} {:.2} {:?} shares @ ${:.4} (order_id: {})",
                     match trade.side {
                         TradeSide::BUY => "BUY",
                         TradeSide::SELL => "SELL",
                     },
                     resp.filled_shares,
+                    trade.outcome,
                     resp.avg_fill_price,
                     resp.order_id
                 );
@@ -46,112 +85,462 @@ impl TradeExecutor {
                 tracing::error!("Trade execution failed: {}", e);
             }
         }
-        
+
         result
     }
-    
-    async fn execute_with_retry(&self, order: OrderRequest) -> Result<OrderResponse> {
+
+    // Submissions reuse the same client_order_id, so the exchange can dedupe a
+    // retry whose original request actually landed. Once the exchange has
+    // acknowledged an order we never submit again, only poll its status.
+    async fn execute_with_retry(&self, trade: &Trade, order: OrderRequest) -> Result<OrderResponse> {
         let mut attempts = 0;
-        let mut last_error = None;
-        
-        while attempts < self.config.retry_attempts {
+
+        loop {
             attempts += 1;
-            
+
             match self.api.place_order(order.clone(), &self.config.private_key).await {
-                Ok(resp) => {
-                    if resp.status == "filled" || resp.status == "partially_filled" {
-                        return Ok(resp);
-                    }
-                    
-                    if resp.status == "cancelled" || resp.status == "rejected" {
-                        anyhow::bail!("Order {} by exchange: {}", resp.status, resp.order_id);
-                    }
+                Ok(resp) => return self.await_final_status(trade, &order, resp).await,
+                Err(e) if !e.is_retryable() || attempts >= self.config.retry_attempts => {
+                    return Err(anyhow::Error::new(e).context(format!(
+                        "Failed to place order {} after {} attempt(s)",
+                        order.client_order_id,
+                        attempts
+                    )));
+                }
+                Err(e) => {
+                    let delay = e.retry_after()
+                        .unwrap_or_else(|| backoff_delay(self.config.retry_delay_ms, attempts));
+
+                    tracing::warn!(
+                        "Attempt {}/{} failed ({}), retrying in {}ms...",
+                        attempts,
+                        self.config.retry_attempts,
+                        e,
+                        delay.as_millis()
+                    );
+
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    async fn await_final_status(&self, trade: &Trade, order: &OrderRequest, mut resp: OrderResponse) -> Result<OrderResponse> {
+        let mut polls = 0;
+
+        loop {
+            match resp.status {
+                // Partial fills are final for FAK; for resting orders the remainder stays on the book
+                OrderStatus::Filled | OrderStatus::PartiallyFilled => return Ok(resp),
+                OrderStatus::Cancelled | OrderStatus::Rejected | OrderStatus::Expired => {
+                    anyhow::bail!("Order {} {} by exchange", resp.order_id, resp.status);
+                }
+                OrderStatus::Open if order.order_type.rests_on_book() => return Ok(resp),
+                OrderStatus::Pending | OrderStatus::Open | OrderStatus::Unknown => {}
+            }
+
+            if polls >= self.config.retry_attempts {
+                return self.give_up(trade, order, resp, polls).await;
+            }
+            polls += 1;
+
+            tokio::time::sleep(backoff_delay(self.config.retry_delay_ms, polls)).await;
+
+            match self.api.get_order(&resp.order_id, &self.config.private_key).await {
+                Ok(latest) => resp = latest,
+                Err(e) if e.is_retryable() => {
+                    tracing::warn!("Status poll for order {} failed: {}", resp.order_id, e);
                 }
                 Err(e) => {
-                    last_error = Some(e);
-                    
-                    if attempts < self.config.retry_attempts {
-                        tracing::warn!(
-                            "Attempt {}/{} failed, retrying in {}ms...",
-                            attempts,
-                            self.config.retry_attempts,
-                            self.config.retry_delay_ms
-                        );
-                        
-                        tokio::time::sleep(Duration::from_millis(
-                            self.config.retry_delay_ms * (attempts as u64)
-                        )).await;
-                    }
+                    return Err(anyhow::Error::new(e))
+                        .context(format!("Failed to poll order {}", resp.order_id));
                 }
             }
         }
-        
-        Err(last_error.unwrap().context(format!(
-            "Failed to execute order after {} attempts",
-            self.config.retry_attempts
-        )))
-    }
-    
-    pub async fn execute_market_order(&self, trade: &Trade, usd_amount: f64) -> Result<OrderResponse> {
+    }
+
+    // An order we stop waiting on can still fill. Cancel it and keep whatever
+    // filled; if we can't learn how it ended, the order manager tracks it so
+    // later fills still reach the risk manager.
+    async fn give_up(&self, trade: &Trade, order: &OrderRequest, resp: OrderResponse, polls: u32) -> Result<OrderResponse> {
+        let key = &self.config.private_key;
+        let settled = match self.api.cancel_order(&resp.order_id, key).await {
+            Ok(_) => self.api.get_order(&resp.order_id, key).await,
+            Err(e) => Err(e),
+        };
+
+        match settled {
+            Ok(latest) if latest.filled_shares > 0.0 => {
+                tracing::warn!("Order {} still {} after {} status polls, cancelled the rest after {:.2} filled",
+                    resp.order_id, resp.status, polls, latest.filled_shares);
+                Ok(latest)
+            }
+            Ok(_) => anyhow::bail!(
+                "Order {} still {} after {} status polls, cancelled unfilled",
+                resp.order_id,
+                resp.status,
+                polls
+            ),
+            Err(e) => {
+                self.orders.track(trade, order, &resp);
+                anyhow::bail!(
+                    "Order {} still {} after {} status polls and couldn't be cancelled ({}), left to the order manager",
+                    resp.order_id,
+                    resp.status,
+                    polls,
+                    e
+                )
+            }
+        }
+    }
+
+    pub async fn execute_market_order(&self, trade: &Trade, market: &Market, usd_amount: f64) -> Result<OrderResponse> {
+        let token_id = resolve_token(trade, market)?;
         let shares = if trade.price > 0.0 {
             usd_amount / trade.price
         } else {
             anyhow::bail!("Invalid price: {}", trade.price);
         };
-        
+
         let order = OrderRequest {
+            client_order_id: new_client_order_id(),
             market_id: trade.market_id.clone(),
+            token_id,
+            outcome: trade.outcome,
             side: trade.side.clone(),
             shares,
             price: None,  // Market order
             order_type: OrderType::MARKET,
+            expiration: None,
+            neg_risk: market.neg_risk,
         };
-        
-        self.execute_with_retry(order).await
+
+        self.execute_with_retry(trade, order).await
     }
-    
-    pub async fn close_position(&self, market_id: &str, shares: f64, side: TradeSide) -> Result<OrderResponse> {
+
+    // Our side of closing a position, as the risk manager books it
+    pub fn exit_trade(&self, market: &Market, outcome: Outcome, shares: f64, side: TradeSide) -> Trade {
         // To close a BUY position, we SELL
         // To close a SELL position, we BUY
         let close_side = match side {
             TradeSide::BUY => TradeSide::SELL,
             TradeSide::SELL => TradeSide::BUY,
         };
-        
-        let order = OrderRequest {
-            market_id: market_id.to_string(),
+
+        Trade {
+            wallet: self.config.your_wallet.clone(),
+            event_id: market.event_id.clone(),
+            market_id: market.id.clone(),
+            outcome,
+            token_id: Some(market.token_id(outcome).to_string()),
             side: close_side,
             shares,
+            price: market.price(outcome),
+            timestamp: chrono::Utc::now().timestamp(),
+            tx_hash: None,
+        }
+    }
+
+    pub async fn close_position(&self, market: &Market, outcome: Outcome, shares: f64, side: TradeSide) -> Result<OrderResponse> {
+        let exit = self.exit_trade(market, outcome, shares, side);
+
+        let order = OrderRequest {
+            client_order_id: new_client_order_id(),
+            market_id: market.id.clone(),
+            token_id: market.token_id(outcome).to_string(),
+            outcome,
+            side: exit.side.clone(),
+            shares,
             price: None,
             order_type: OrderType::MARKET,
+            expiration: None,
+            neg_risk: market.neg_risk,
         };
-        
-        tracing::info!("Closing position: {} {:.2} shares on {}", 
-            match side {
-                TradeSide::BUY => "SELL",
-                TradeSide::SELL => "BUY",
+
+        tracing::info!("Closing position: {} {:.2} {:?} shares on {}",
+            match exit.side {
+                TradeSide::BUY => "BUY",
+                TradeSide::SELL => "SELL",
             },
-            shares, 
-            market_id
+            shares,
+            outcome,
+            market.id
         );
-        
-        self.execute_with_retry(order).await
+
+        self.execute_with_retry(&exit, order).await
     }
-    
-    pub async fn get_estimated_price(&self, market_id: &str, side: &TradeSide) -> Result<f64> {
-        let (bids, asks) = self.api.get_orderbook(market_id).await?;
-        
+
+    pub async fn get_estimated_price(&self, token_id: &str, side: &TradeSide) -> Result<f64> {
+        Ok(self.best_price(token_id, side).await?.unwrap_or(0.5))
+    }
+
+    // Top of the book we'd trade against, or None when that side is empty
+    pub async fn best_price(&self, token_id: &str, side: &TradeSide) -> Result<Option<f64>> {
+        if let Some(price) = self.books.as_ref().and_then(|b| b.with_book(token_id, |book| book.best(side))) {
+            return Ok(price);
+        }
+
+        let (bids, asks) = self.api.get_orderbook(token_id).await?;
+
         let price = match side {
             TradeSide::BUY => {
                 // For buying, we look at asks (sellers)
-                asks.first().map(|(p, _)| *p).unwrap_or(0.5)
+                asks.first().map(|(p, _)| *p)
             }
             TradeSide::SELL => {
                 // For selling, we look at bids (buyers)
-                bids.first().map(|(p, _)| *p).unwrap_or(0.5)
+                bids.first().map(|(p, _)| *p)
             }
         };
-        
+
         Ok(price)
     }
 }
+
+// The order must hit the outcome token the whale actually traded
+fn resolve_token(trade: &Trade, market: &Market) -> Result<String> {
+    if trade.market_id != market.id {
+        anyhow::bail!("Trade is for market {} but market data is for {}", trade.market_id, market.id);
+    }
+
+    let expected = market.token_id(trade.outcome);
+    if let Some(token_id) = &trade.token_id {
+        if token_id != expected {
+            anyhow::bail!(
+                "Trade token {} is not the {:?} token ({}) of market {}",
+                token_id,
+                trade.outcome,
+                expected,
+                market.id
+            );
+        }
+    }
+
+    Ok(expected.to_string())
+}
+
+pub fn new_client_order_id() -> String {
+    format!(
+        "copy-{}-{:016x}",
+        chrono::Utc::now().timestamp_millis(),
+        rand::thread_rng().gen::<u64>()
+    )
+}
+
+// Exponential backoff with "equal jitter": half the window is fixed, half random,
+// so concurrent retries spread out without ever collapsing to zero delay
+fn backoff_delay(base_ms: u64, attempt: u32) -> Duration {
+    let window = base_ms.saturating_mul(1u64 << attempt.saturating_sub(1).min(10));
+    let half = window / 2;
+    Duration::from_millis(half + rand::thread_rng().gen_range(0..=window - half))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::{fixtures, response, MockExchange};
+    use crate::error::ApiError;
+
+    fn test_config() -> Config {
+        Config {
+            retry_attempts: 3,
+            retry_delay_ms: 1,
+            ..Default::default()
+        }
+    }
+
+    fn market() -> Market {
+        fixtures::market("market1")
+    }
+
+    fn buy_trade() -> Trade {
+        fixtures::trade("market1")
+    }
+
+    #[tokio::test]
+    async fn test_pending_order_is_polled_not_resubmitted() {
+        let exchange = MockExchange::default();
+        exchange.on_place(Ok(response("o1", OrderStatus::Pending, 0.0, 0.0)));
+        exchange.on_get_order(Ok(response("o1", OrderStatus::Open, 0.0, 0.0)));
+        exchange.on_get_order(Ok(response("o1", OrderStatus::Filled, 10.0, 0.5)));
+
+        let executor = TradeExecutor::new(exchange.clone(), test_config());
+        let resp = executor.execute_trade(&buy_trade(), &market(), 10.0).await.unwrap();
+
+        assert_eq!(resp.status, OrderStatus::Filled);
+        assert_eq!(exchange.placed().len(), 1);
+        assert_eq!(exchange.polled(), vec!["o1", "o1"]);
+    }
+
+    #[tokio::test]
+    async fn test_order_stuck_pending_errors_without_panic() {
+        let exchange = MockExchange::default();
+        exchange.on_place(Ok(response("o1", OrderStatus::Pending, 0.0, 0.0)));
+        for _ in 0..3 {
+            exchange.on_get_order(Ok(response("o1", OrderStatus::Pending, 0.0, 0.0)));
+        }
+
+        let executor = TradeExecutor::new(exchange.clone(), test_config());
+        let err = executor.execute_trade(&buy_trade(), &market(), 10.0).await.unwrap_err();
+
+        assert!(err.to_string().contains("still pending"));
+        assert_eq!(exchange.placed().len(), 1);
+        assert_eq!(exchange.cancelled(), vec!["o1"]);
+    }
+
+    #[tokio::test]
+    async fn test_order_filling_after_polls_run_out_is_kept() {
+        let exchange = MockExchange::default();
+        exchange.on_place(Ok(response("o1", OrderStatus::Pending, 0.0, 0.0)));
+        for _ in 0..3 {
+            exchange.on_get_order(Ok(response("o1", OrderStatus::Pending, 0.0, 0.0)));
+        }
+        // Part of it filled before the cancel landed
+        exchange.on_get_order(Ok(response("o1", OrderStatus::Cancelled, 4.0, 0.5)));
+
+        let executor = TradeExecutor::new(exchange.clone(), test_config());
+        let resp = executor.execute_trade(&buy_trade(), &market(), 10.0).await.unwrap();
+
+        assert_eq!((resp.filled_shares, resp.avg_fill_price), (4.0, 0.5));
+        assert_eq!(exchange.cancelled(), vec!["o1"]);
+        assert!(executor.orders().open_orders().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_uncancellable_order_is_left_to_the_order_manager() {
+        let exchange = MockExchange::default();
+        exchange.on_place(Ok(response("o1", OrderStatus::Pending, 0.0, 0.0)));
+        for _ in 0..3 {
+            exchange.on_get_order(Ok(response("o1", OrderStatus::Pending, 0.0, 0.0)));
+        }
+        exchange.on_cancel(Err(ApiError::Network("connection reset".to_string())));
+
+        let executor = TradeExecutor::new(exchange.clone(), test_config());
+        assert!(executor.execute_trade(&buy_trade(), &market(), 10.0).await.is_err());
+        assert_eq!(executor.orders().open_orders().len(), 1);
+
+        // The fill that lands later is still booked
+        exchange.on_get_order(Ok(response("o1", OrderStatus::Filled, 10.0, 0.5)));
+        executor.orders().poll_open_orders().await;
+        let fills = executor.orders().take_fills();
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].1.filled_shares, 10.0);
+        assert!(executor.orders().open_orders().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_retries_reuse_client_order_id() {
+        let exchange = MockExchange::default();
+        exchange.on_place(Err(ApiError::Network("connection reset".to_string())));
+        exchange.on_place(Err(ApiError::from_status(503, None, String::new())));
+        exchange.on_place(Ok(response("o1", OrderStatus::Filled, 10.0, 0.5)));
+
+        let executor = TradeExecutor::new(exchange.clone(), test_config());
+        executor.execute_trade(&buy_trade(), &market(), 10.0).await.unwrap();
+
+        let placed = exchange.placed();
+        assert_eq!(placed.len(), 3);
+        assert!(placed.iter().all(|o| o.client_order_id == placed[0].client_order_id));
+    }
+
+    #[tokio::test]
+    async fn test_non_retryable_error_fails_fast() {
+        let exchange = MockExchange::default();
+        exchange.on_place(Err(ApiError::from_status(401, None, "bad key".to_string())));
+
+        let executor = TradeExecutor::new(exchange.clone(), test_config());
+        assert!(executor.execute_trade(&buy_trade(), &market(), 10.0).await.is_err());
+        assert_eq!(exchange.placed().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_terminal_statuses() {
+        for status in [OrderStatus::Cancelled, OrderStatus::Rejected, OrderStatus::Expired] {
+            let exchange = MockExchange::default();
+            exchange.on_place(Ok(response("o1", status.clone(), 0.0, 0.0)));
+
+            let executor = TradeExecutor::new(exchange.clone(), test_config());
+            let err = executor.execute_trade(&buy_trade(), &market(), 10.0).await.unwrap_err();
+            assert!(err.to_string().contains(status.as_str()));
+            assert!(exchange.polled().is_empty());
+        }
+
+        // A resting GTD sell is an accepted outcome, not something to wait on
+        let exchange = MockExchange::default();
+        exchange.on_place(Ok(response("o2", OrderStatus::Open, 0.0, 0.0)));
+        let executor = TradeExecutor::new(exchange.clone(), test_config());
+        let sell = Trade { side: TradeSide::SELL, ..buy_trade() };
+        let resp = executor.execute_trade(&sell, &market(), 10.0).await.unwrap();
+        assert_eq!(resp.status, OrderStatus::Open);
+        assert!(exchange.polled().is_empty());
+        assert_eq!(executor.orders().open_orders().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_inverted_trade_routes_to_opposite_token() {
+        let exchange = MockExchange::default();
+        exchange.on_place(Ok(response("o1", OrderStatus::Filled, 10.0, 0.7)));
+
+        // Whale buys YES at 0.30 on its YES token; we buy NO at 0.70
+        let whale = Trade { price: 0.3, token_id: Some("tok-yes".to_string()), ..buy_trade() };
+        let faded = whale.inverted();
+        assert_eq!(faded.outcome, Outcome::No);
+        assert!((faded.price - 0.7).abs() < 1e-9);
+        assert_eq!(faded.wallet, whale.wallet);
+
+        let executor = TradeExecutor::new(exchange.clone(), test_config());
+        executor.execute_trade(&faded, &market(), 10.0).await.unwrap();
+
+        let placed = exchange.placed();
+        assert_eq!(placed[0].token_id, "tok-no");
+        assert_eq!(placed[0].side, TradeSide::BUY);
+        assert!((placed[0].price.unwrap() - 0.7).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_no_buy_routes_to_no_token() {
+        let exchange = MockExchange::default();
+        exchange.on_place(Ok(response("o1", OrderStatus::Filled, 10.0, 0.3)));
+
+        let executor = TradeExecutor::new(exchange.clone(), test_config());
+        let trade = Trade { outcome: Outcome::No, price: 0.3, ..buy_trade() };
+        executor.execute_trade(&trade, &market(), 10.0).await.unwrap();
+
+        let placed = exchange.placed();
+        assert_eq!(placed[0].token_id, "tok-no");
+        assert_eq!(placed[0].outcome, Outcome::No);
+        assert!(!placed[0].neg_risk);
+    }
+
+    #[tokio::test]
+    async fn test_neg_risk_market_routes_to_neg_risk_exchange() {
+        let exchange = MockExchange::default();
+        exchange.on_place(Ok(response("o1", OrderStatus::Filled, 10.0, 0.5)));
+
+        let executor = TradeExecutor::new(exchange.clone(), test_config());
+        let market = Market { neg_risk: true, ..market() };
+        executor.execute_trade(&buy_trade(), &market, 10.0).await.unwrap();
+
+        assert!(exchange.placed()[0].neg_risk);
+    }
+
+    #[tokio::test]
+    async fn test_token_mismatch_is_rejected() {
+        let exchange = MockExchange::default();
+        let executor = TradeExecutor::new(exchange.clone(), test_config());
+
+        // Feed says NO but the asset id is the YES token: refuse rather than guess
+        let trade = Trade { outcome: Outcome::No, token_id: Some("tok-yes".to_string()), ..buy_trade() };
+        assert!(executor.execute_trade(&trade, &market(), 10.0).await.is_err());
+        assert!(exchange.placed().is_empty());
+    }
+
+    #[test]
+    fn test_backoff_delay_bounds() {
+        for attempt in 1..=5 {
+            let window = 100 * (1 << (attempt - 1));
+            let delay = backoff_delay(100, attempt).as_millis() as u64;
+            assert!(delay >= window / 2 && delay <= window);
+        }
+    }
+}
@@ -0,0 +1,448 @@
+use crate::types::{Event, Market, Outcome, Trade, TradeSide};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OutcomePosition {
+    pub shares: f64,
+    pub cost: f64,  // USD paid for the shares still held, fees included
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MarketPosition {
+    pub event_id: String,
+    pub yes: OutcomePosition,
+    pub no: OutcomePosition,
+}
+
+impl MarketPosition {
+    pub fn outcome(&self, outcome: Outcome) -> &OutcomePosition {
+        match outcome {
+            Outcome::Yes => &self.yes,
+            Outcome::No => &self.no,
+        }
+    }
+
+    fn outcome_mut(&mut self, outcome: Outcome) -> &mut OutcomePosition {
+        match outcome {
+            Outcome::Yes => &mut self.yes,
+            Outcome::No => &mut self.no,
+        }
+    }
+
+    fn is_flat(&self) -> bool {
+        self.yes == OutcomePosition::default() && self.no == OutcomePosition::default()
+    }
+
+    // Worst-case loss over both resolutions. Each YES+NO pair pays out $1
+    // whatever happens, so holding both sides offsets.
+    pub fn capital_at_risk(&self) -> f64 {
+        let cost = self.yes.cost + self.no.cost;
+        (cost - self.yes.shares.min(self.no.shares)).max(0.0)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct EventInfo {
+    neg_risk: bool,
+    market_ids: HashSet<String>,
+}
+
+// Net position per market outcome, built from our own fills
+#[derive(Debug, Clone, Default)]
+pub struct ExposureBook {
+    markets: HashMap<String, MarketPosition>,
+    events: HashMap<String, EventInfo>,
+    realized_pnl: f64,
+    marks: HashMap<String, (f64, f64)>,  // Latest (YES, NO) price per market
+    // The same positions split by the whale whose trade opened them, keyed
+    // by lowercase wallet
+    by_wallet: HashMap<String, HashMap<String, MarketPosition>>,
+    realized_by_wallet: HashMap<String, f64>,
+}
+
+impl ExposureBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Books one of our own fills on `trade`'s market outcome and side,
+    // attributed to `trade.wallet`. Returns the PnL realised by it (always
+    // zero for buys).
+    pub fn apply_fill(&mut self, trade: &Trade, shares: f64, price: f64, fees: f64) -> f64 {
+        let market_id = trade.market_id.as_str();
+        let outcome = trade.outcome;
+        let whale = trade.wallet.to_lowercase();
+
+        let realized = match trade.side {
+            TradeSide::BUY => {
+                let cost = shares * price + fees;
+                for book in [&mut self.markets, self.by_wallet.entry(whale.clone()).or_default()] {
+                    let position = book.entry(market_id.to_string()).or_insert_with(|| MarketPosition {
+                        event_id: trade.event_id.clone(),
+                        ..Default::default()
+                    }).outcome_mut(outcome);
+                    position.shares += shares;
+                    position.cost += cost;
+                }
+                0.0
+            }
+            TradeSide::SELL => {
+                // Outcome tokens can't be shorted, so never sell more than we hold
+                let held = self.position(market_id).map_or(0.0, |m| m.outcome(outcome).shares);
+                let sold = shares.min(held);
+                if sold <= 0.0 {
+                    tracing::warn!("Sell of {:.2} {:?} shares on {} with no position held", shares, outcome, market_id);
+                    return 0.0;
+                }
+                let fee_per_share = fees / sold;
+                let mut cost_removed = 0.0;
+
+                // Release the selling whale's shares first, then anyone else's
+                let mut wallets: Vec<String> = self.by_wallet.keys().filter(|w| **w != whale).cloned().collect();
+                wallets.sort();
+                wallets.insert(0, whale);
+                let mut remaining = sold;
+                for wallet in wallets {
+                    if remaining <= 0.0 {
+                        break;
+                    }
+                    let Some(book) = self.by_wallet.get_mut(&wallet) else { continue };
+                    let Some(lot) = book.get_mut(market_id) else { continue };
+                    let (taken, cost) = reduce(lot.outcome_mut(outcome), remaining);
+                    if taken <= 0.0 {
+                        continue;
+                    }
+                    if lot.is_flat() {
+                        book.remove(market_id);
+                    }
+                    remaining -= taken;
+                    cost_removed += cost;
+                    *self.realized_by_wallet.entry(wallet).or_default() += taken * (price - fee_per_share) - cost;
+                }
+
+                let position = self.markets.get_mut(market_id).unwrap().outcome_mut(outcome);
+                if remaining > 0.0 {
+                    // Shares with no recorded owner go at the pooled average cost
+                    cost_removed += position.cost / position.shares * remaining;
+                }
+                position.shares -= sold;
+                position.cost -= cost_removed;
+                if position.shares <= f64::EPSILON {
+                    *position = OutcomePosition::default();
+                }
+                sold * price - fees - cost_removed
+            }
+        };
+
+        if self.markets.get(market_id).is_some_and(|m| m.is_flat()) {
+            self.markets.remove(market_id);
+        }
+
+        self.realized_pnl += realized;
+        realized
+    }
+
+    pub fn position(&self, market_id: &str) -> Option<&MarketPosition> {
+        self.markets.get(market_id)
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = (&String, &MarketPosition)> {
+        self.markets.iter()
+    }
+
+    pub fn register_event(&mut self, event: &Event) {
+        self.events.insert(event.id.clone(), EventInfo {
+            neg_risk: event.neg_risk,
+            market_ids: event.markets.iter().map(|m| m.id.clone()).collect(),
+        });
+    }
+
+    pub fn knows_event(&self, event_id: &str) -> bool {
+        self.events.contains_key(event_id)
+    }
+
+    pub fn event_at_risk(&self, event_id: &str) -> f64 {
+        let positions: Vec<(&str, &MarketPosition)> = self.markets.iter()
+            .filter(|(_, m)| m.event_id == event_id)
+            .map(|(id, m)| (id.as_str(), m))
+            .collect();
+        self.at_risk(event_id, &positions)
+    }
+
+    fn at_risk(&self, event_id: &str, positions: &[(&str, &MarketPosition)]) -> f64 {
+        match self.events.get(event_id) {
+            Some(event) if event.neg_risk => neg_risk_at_risk(event, positions),
+            // Independent markets: every one of them can go against us
+            _ => positions.iter().map(|(_, m)| m.capital_at_risk()).sum(),
+        }
+    }
+
+    pub fn market_at_risk(&self, market_id: &str) -> f64 {
+        self.markets.get(market_id).map_or(0.0, |m| m.capital_at_risk())
+    }
+
+    pub fn projected_market_at_risk(&self, market_id: &str, outcome: Outcome, shares: f64, cost: f64) -> f64 {
+        let mut projected = self.markets.get(market_id).cloned().unwrap_or_default();
+        let position = projected.outcome_mut(outcome);
+        position.shares += shares;
+        position.cost += cost;
+        projected.capital_at_risk()
+    }
+
+    pub fn open_positions(&self) -> usize {
+        self.markets.len()
+    }
+
+    // Event exposure as it would be after buying `shares` of `outcome` for `cost`
+    pub fn projected_event_at_risk(
+        &self,
+        event_id: &str,
+        market_id: &str,
+        outcome: Outcome,
+        shares: f64,
+        cost: f64,
+    ) -> f64 {
+        let mut projected = self.markets.get(market_id).cloned().unwrap_or_else(|| MarketPosition {
+            event_id: event_id.to_string(),
+            ..Default::default()
+        });
+        let position = projected.outcome_mut(outcome);
+        position.shares += shares;
+        position.cost += cost;
+
+        let positions: Vec<(&str, &MarketPosition)> = self.markets.iter()
+            .filter(|(id, m)| m.event_id == event_id && id.as_str() != market_id)
+            .map(|(id, m)| (id.as_str(), m))
+            .chain(std::iter::once((market_id, &projected)))
+            .collect();
+        self.at_risk(event_id, &positions)
+    }
+
+    pub fn total_cost(&self) -> f64 {
+        self.markets.values().map(|m| m.yes.cost + m.no.cost).sum()
+    }
+
+    pub fn mark(&mut self, market: &Market) {
+        self.marks.insert(market.id.clone(), (market.yes_price, market.no_price));
+    }
+
+    // Positions valued at their latest marks; unmarked ones count at cost
+    pub fn market_value(&self) -> f64 {
+        self.markets.iter().map(|(id, m)| match self.marks.get(id) {
+            Some((yes, no)) => m.yes.shares * yes + m.no.shares * no,
+            None => m.yes.cost + m.no.cost,
+        }).sum()
+    }
+
+    pub fn unrealized_pnl(&self) -> f64 {
+        self.market_value() - self.total_cost()
+    }
+
+    pub fn realized_pnl(&self) -> f64 {
+        self.realized_pnl
+    }
+
+    // Capital at risk in positions opened by copying `wallet`
+    pub fn wallet_at_risk(&self, wallet: &str) -> f64 {
+        self.by_wallet.get(&wallet.to_lowercase())
+            .map_or(0.0, |book| book.values().map(|m| m.capital_at_risk()).sum())
+    }
+
+    pub fn projected_wallet_at_risk(&self, wallet: &str, market_id: &str, outcome: Outcome, shares: f64, cost: f64) -> f64 {
+        let book = self.by_wallet.get(&wallet.to_lowercase());
+        let mut projected = book.and_then(|b| b.get(market_id)).cloned().unwrap_or_default();
+        let position = projected.outcome_mut(outcome);
+        position.shares += shares;
+        position.cost += cost;
+
+        self.wallet_at_risk(wallet) - book.and_then(|b| b.get(market_id)).map_or(0.0, |m| m.capital_at_risk())
+            + projected.capital_at_risk()
+    }
+
+    pub fn wallet_positions(&self, wallet: &str) -> impl Iterator<Item = (&String, &MarketPosition)> {
+        self.by_wallet.get(&wallet.to_lowercase()).into_iter().flat_map(|book| book.iter())
+    }
+
+    pub fn wallet_realized_pnl(&self, wallet: &str) -> f64 {
+        self.realized_by_wallet.get(&wallet.to_lowercase()).copied().unwrap_or(0.0)
+    }
+}
+
+// Takes up to `shares` off a position at its average cost; returns (taken, cost removed)
+fn reduce(position: &mut OutcomePosition, shares: f64) -> (f64, f64) {
+    let taken = shares.min(position.shares);
+    if taken <= 0.0 {
+        return (0.0, 0.0);
+    }
+    let cost = position.cost * taken / position.shares;
+    position.shares -= taken;
+    position.cost -= cost;
+    if position.shares <= f64::EPSILON {
+        *position = OutcomePosition::default();
+    }
+    (taken, cost)
+}
+
+// Worst-case loss when at most one market in the event resolves YES: in the
+// scenario where market i wins, its YES shares pay and so does every NO share
+// held on the other markets.
+fn neg_risk_at_risk(event: &EventInfo, positions: &[(&str, &MarketPosition)]) -> f64 {
+    if positions.is_empty() {
+        return 0.0;
+    }
+
+    let cost: f64 = positions.iter().map(|(_, m)| m.yes.cost + m.no.cost).sum();
+    let no_shares: f64 = positions.iter().map(|(_, m)| m.no.shares).sum();
+
+    let mut worst_payout = positions.iter()
+        .map(|(_, m)| m.yes.shares + no_shares - m.no.shares)
+        .fold(f64::INFINITY, f64::min);
+
+    // A market we hold nothing in can win too (or none at all), paying only our NOs
+    let held: HashSet<&str> = positions.iter().map(|(id, _)| *id).collect();
+    if event.market_ids.iter().any(|id| !held.contains(id.as_str())) || event.market_ids.is_empty() {
+        worst_payout = worst_payout.min(no_shares);
+    }
+
+    (cost - worst_payout).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::fixtures;
+
+    fn fill(book: &mut ExposureBook, event_id: &str, market_id: &str, outcome: Outcome, side: TradeSide, shares: f64, price: f64) -> f64 {
+        fill_as(book, "0xwhale", event_id, market_id, outcome, side, shares, price)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn fill_as(book: &mut ExposureBook, wallet: &str, event_id: &str, market_id: &str, outcome: Outcome, side: TradeSide, shares: f64, price: f64) -> f64 {
+        let trade = Trade {
+            wallet: wallet.to_string(),
+            event_id: event_id.to_string(),
+            outcome,
+            side,
+            shares,
+            price,
+            ..fixtures::trade(market_id)
+        };
+        book.apply_fill(&trade, shares, price, 0.0)
+    }
+
+    #[test]
+    fn test_sells_net_against_buys() {
+        let mut book = ExposureBook::new();
+        fill(&mut book, "event1", "market1", Outcome::Yes, TradeSide::BUY, 100.0, 0.5);
+        assert_eq!(book.event_at_risk("event1"), 50.0);
+
+        let realized = fill(&mut book, "event1", "market1", Outcome::Yes, TradeSide::SELL, 40.0, 0.6);
+        assert!((realized - 4.0).abs() < 1e-9);
+        assert!((book.event_at_risk("event1") - 30.0).abs() < 1e-9);
+
+        // Selling out completely leaves nothing at risk
+        fill(&mut book, "event1", "market1", Outcome::Yes, TradeSide::SELL, 100.0, 0.6);
+        assert_eq!(book.event_at_risk("event1"), 0.0);
+        assert!(book.position("market1").is_none());
+    }
+
+    #[test]
+    fn test_yes_and_no_offset() {
+        let mut book = ExposureBook::new();
+        fill(&mut book, "event1", "market1", Outcome::Yes, TradeSide::BUY, 100.0, 0.6);
+        fill(&mut book, "event1", "market1", Outcome::No, TradeSide::BUY, 100.0, 0.35);
+
+        // 100 YES + 100 NO always pays $100 for $95 spent
+        assert_eq!(book.event_at_risk("event1"), 0.0);
+
+        // 50 more YES for $30: a NO resolution now pays $100 against $125 spent
+        let projected = book.projected_event_at_risk("event1", "market1", Outcome::Yes, 50.0, 30.0);
+        assert!((projected - 25.0).abs() < 1e-9);
+    }
+
+    fn neg_risk_event(markets: &[&str]) -> Event {
+        Event {
+            id: "election".to_string(),
+            neg_risk: true,
+            markets: markets.iter().map(|id| Market {
+                event_id: "election".to_string(),
+                neg_risk: true,
+                ..fixtures::market(id)
+            }).collect(),
+        }
+    }
+
+    #[test]
+    fn test_neg_risk_yes_on_every_outcome_is_hedged() {
+        let mut book = ExposureBook::new();
+        book.register_event(&neg_risk_event(&["alice", "bob", "carol"]));
+        fill(&mut book, "election", "alice", Outcome::Yes, TradeSide::BUY, 100.0, 0.5);
+        fill(&mut book, "election", "bob", Outcome::Yes, TradeSide::BUY, 100.0, 0.3);
+
+        // Carol can still win and pay nothing
+        assert!((book.event_at_risk("election") - 80.0).abs() < 1e-9);
+
+        // Covering carol too means exactly one $100 payout whatever happens
+        fill(&mut book, "election", "carol", Outcome::Yes, TradeSide::BUY, 100.0, 0.2);
+        assert!(book.event_at_risk("election").abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_neg_risk_no_positions_cannot_all_lose() {
+        let mut book = ExposureBook::new();
+        book.register_event(&neg_risk_event(&["alice", "bob", "carol"]));
+        fill(&mut book, "election", "alice", Outcome::No, TradeSide::BUY, 100.0, 0.6);
+        fill(&mut book, "election", "bob", Outcome::No, TradeSide::BUY, 100.0, 0.7);
+
+        // Summing notional says $130, but at least one NO always pays $100
+        assert!((book.event_at_risk("election") - 30.0).abs() < 1e-9);
+
+        let projected = book.projected_event_at_risk("election", "carol", Outcome::No, 100.0, 90.0);
+        assert!((projected - 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_unrealized_pnl_uses_marks() {
+        let mut book = ExposureBook::new();
+        fill(&mut book, "event1", "market1", Outcome::Yes, TradeSide::BUY, 100.0, 0.5);
+        fill(&mut book, "event1", "market2", Outcome::No, TradeSide::BUY, 50.0, 0.4);
+        assert_eq!(book.unrealized_pnl(), 0.0);
+
+        let mut market = neg_risk_event(&["market1"]).markets.remove(0);
+        market.yes_price = 0.3;
+        market.no_price = 0.7;
+        book.mark(&market);
+        assert!((book.unrealized_pnl() + 20.0).abs() < 1e-9);
+        assert!((book.market_value() - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_positions_attributed_to_whales() {
+        let mut book = ExposureBook::new();
+        fill_as(&mut book, "0xa", "event1", "market1", Outcome::Yes, TradeSide::BUY, 100.0, 0.5);
+        fill_as(&mut book, "0xb", "event1", "market1", Outcome::Yes, TradeSide::BUY, 100.0, 0.3);
+        assert_eq!(book.wallet_at_risk("0xa"), 50.0);
+        assert_eq!(book.wallet_at_risk("0xb"), 30.0);
+        assert!((book.projected_wallet_at_risk("0xb", "market1", Outcome::No, 100.0, 60.0) - 0.0).abs() < 1e-9);
+
+        // A whale selling more than it opened eats into the other's shares
+        let realized = fill_as(&mut book, "0xa", "event1", "market1", Outcome::Yes, TradeSide::SELL, 150.0, 0.6);
+        assert!((realized - 25.0).abs() < 1e-9);
+        assert!((book.wallet_realized_pnl("0xa") - 10.0).abs() < 1e-9);
+        assert!((book.wallet_realized_pnl("0xb") - 15.0).abs() < 1e-9);
+        assert_eq!(book.wallet_at_risk("0xa"), 0.0);
+        assert!((book.wallet_at_risk("0xb") - 15.0).abs() < 1e-9);
+        assert!((book.event_at_risk("event1") - 15.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_event_sums_markets() {
+        let mut book = ExposureBook::new();
+        fill(&mut book, "event1", "market1", Outcome::Yes, TradeSide::BUY, 100.0, 0.5);
+        fill(&mut book, "event1", "market2", Outcome::No, TradeSide::BUY, 10.0, 0.2);
+        fill(&mut book, "event2", "market3", Outcome::Yes, TradeSide::BUY, 10.0, 0.9);
+
+        assert!((book.event_at_risk("event1") - 52.0).abs() < 1e-9);
+        assert!((book.event_at_risk("event2") - 9.0).abs() < 1e-9);
+    }
+}
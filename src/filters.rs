@@ -0,0 +1,272 @@
+use crate::types::{Config, FilterRules, Market, Trade, TradeSide};
+use anyhow::{Context, Result, bail};
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+
+// One rule deciding whether a market is worth copying into
+pub trait MarketFilter: Send + Sync {
+    fn check(&self, trade: &Trade, market: &Market, now: i64) -> Result<()>;
+}
+
+pub struct PriceBand {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl MarketFilter for PriceBand {
+    fn check(&self, trade: &Trade, market: &Market, _now: i64) -> Result<()> {
+        let price = market.price(trade.outcome);
+        if price < self.min || price > self.max {
+            bail!("{:?} price {:.3} outside [{:.2}, {:.2}]", trade.outcome, price, self.min, self.max);
+        }
+        Ok(())
+    }
+}
+
+pub struct ResolutionHorizon {
+    pub min_secs: i64,
+    pub max_secs: i64,
+}
+
+impl MarketFilter for ResolutionHorizon {
+    fn check(&self, _trade: &Trade, market: &Market, now: i64) -> Result<()> {
+        // Markets without an end date can't be judged on it
+        let Some(end_date) = market.end_date else {
+            return Ok(());
+        };
+        let remaining = end_date - now;
+        if remaining < self.min_secs {
+            bail!("Resolves in {:.1}h, sooner than {:.1}h", remaining as f64 / 3600.0, self.min_secs as f64 / 3600.0);
+        }
+        if remaining > self.max_secs {
+            bail!("Resolves in {:.0}d, later than {:.0}d", remaining as f64 / 86400.0, self.max_secs as f64 / 86400.0);
+        }
+        Ok(())
+    }
+}
+
+// Matches the market's category and its tags, case-insensitively
+pub struct CategoryBlock {
+    blocked: Vec<String>,
+}
+
+impl CategoryBlock {
+    pub fn new(blocked: &[String]) -> Self {
+        Self { blocked: blocked.iter().map(|c| c.to_lowercase()).collect() }
+    }
+}
+
+impl MarketFilter for CategoryBlock {
+    fn check(&self, _trade: &Trade, market: &Market, _now: i64) -> Result<()> {
+        let labels = market.category.iter().chain(market.tags.iter());
+        for label in labels {
+            if self.blocked.contains(&label.to_lowercase()) {
+                bail!("Category '{}' is blocked", label);
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct KeywordRules {
+    deny: Option<Regex>,
+    allow: Option<Regex>,
+}
+
+impl KeywordRules {
+    pub fn new(deny: Option<&str>, allow: Option<&str>) -> Result<Self> {
+        let compile = |pattern: &str| RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .with_context(|| format!("Invalid keyword regex '{}'", pattern));
+        Ok(Self {
+            deny: deny.map(compile).transpose()?,
+            allow: allow.map(compile).transpose()?,
+        })
+    }
+}
+
+impl MarketFilter for KeywordRules {
+    fn check(&self, _trade: &Trade, market: &Market, _now: i64) -> Result<()> {
+        if let Some(deny) = &self.deny {
+            if let Some(m) = deny.find(&market.question) {
+                bail!("Question matches denied keyword '{}'", m.as_str());
+            }
+        }
+        if let Some(allow) = &self.allow {
+            if !allow.is_match(&market.question) {
+                bail!("Question matches no allowed keyword");
+            }
+        }
+        Ok(())
+    }
+}
+
+// Filters run in order; the first rejection wins
+#[derive(Default)]
+pub struct FilterChain {
+    filters: Vec<Box<dyn MarketFilter>>,
+}
+
+impl FilterChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, filter: impl MarketFilter + 'static) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    pub fn from_rules(rules: &FilterRules) -> Result<Self> {
+        let mut chain = Self::new()
+            .with(PriceBand { min: rules.min_price, max: rules.max_price })
+            .with(ResolutionHorizon {
+                min_secs: (rules.min_hours_to_resolution * 3600.0) as i64,
+                max_secs: (rules.max_days_to_resolution * 86400.0) as i64,
+            });
+        if !rules.blocked_categories.is_empty() {
+            chain = chain.with(CategoryBlock::new(&rules.blocked_categories));
+        }
+        if rules.deny_keywords.is_some() || rules.allow_keywords.is_some() {
+            chain = chain.with(KeywordRules::new(rules.deny_keywords.as_deref(), rules.allow_keywords.as_deref())?);
+        }
+        Ok(chain)
+    }
+
+    pub fn check(&self, trade: &Trade, market: &Market, now: i64) -> Result<()> {
+        self.filters.iter().try_for_each(|f| f.check(trade, market, now))
+    }
+}
+
+// The default chain plus any per-wallet replacements. Filters only gate
+// entries: we always follow a whale out of a position.
+pub struct MarketFilters {
+    default: FilterChain,
+    by_wallet: HashMap<String, FilterChain>,
+}
+
+impl MarketFilters {
+    pub fn new(config: &Config) -> Result<Self> {
+        let by_wallet = config.wallet_filter_rules.iter()
+            .map(|(wallet, rules)| {
+                let chain = FilterChain::from_rules(rules).with_context(|| format!("Filters for {}", wallet))?;
+                Ok((wallet.clone(), chain))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            default: FilterChain::from_rules(&config.filter_rules)?,
+            by_wallet,
+        })
+    }
+
+    pub fn check(&self, trade: &Trade, market: &Market, now: i64) -> Result<()> {
+        if trade.side == TradeSide::SELL {
+            return Ok(());
+        }
+        self.by_wallet.get(&trade.wallet.to_lowercase())
+            .unwrap_or(&self.default)
+            .check(trade, market, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::fixtures;
+    use crate::types::Outcome;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn trade(wallet: &str, outcome: Outcome) -> Trade {
+        Trade {
+            wallet: wallet.to_string(),
+            outcome,
+            timestamp: NOW,
+            ..fixtures::trade("market1")
+        }
+    }
+
+    fn market(question: &str, yes_price: f64, days_left: i64) -> Market {
+        Market {
+            question: question.to_string(),
+            yes_price,
+            no_price: 1.0 - yes_price,
+            end_date: Some(NOW + days_left * 86400),
+            category: Some("Politics".to_string()),
+            tags: vec!["elections".to_string()],
+            ..fixtures::market("market1")
+        }
+    }
+
+    fn filters(wallet_rules: HashMap<String, FilterRules>) -> MarketFilters {
+        MarketFilters::new(&Config {
+            filter_rules: FilterRules {
+                blocked_categories: vec!["sports".to_string()],
+                deny_keywords: Some(r"\b(rug|airdrop)\b".to_string()),
+                ..Default::default()
+            },
+            wallet_filter_rules: wallet_rules,
+            ..Default::default()
+        }).unwrap()
+    }
+
+    #[test]
+    fn test_default_rules() {
+        let filters = filters(HashMap::new());
+        let buy = trade("0xwhale", Outcome::Yes);
+        assert!(filters.check(&buy, &market("Will Alice win?", 0.5, 30), NOW).is_ok());
+
+        // Price of the outcome being bought, not always YES
+        assert!(filters.check(&buy, &market("Will Alice win?", 0.97, 30), NOW).is_err());
+        assert!(filters.check(&trade("0xwhale", Outcome::No), &market("Will Alice win?", 0.97, 30), NOW).is_err());
+        assert!(filters.check(&trade("0xwhale", Outcome::No), &market("Will Alice win?", 0.5, 30), NOW).is_ok());
+
+        assert!(filters.check(&buy, &market("Will Alice win?", 0.5, 0), NOW).is_err());
+        assert!(filters.check(&buy, &market("Will Alice win?", 0.5, 1000), NOW).is_err());
+        assert!(filters.check(&buy, &market("Is the airdrop live?", 0.5, 30), NOW).is_err());
+
+        let mut sports = market("Will the Lakers win?", 0.5, 30);
+        sports.tags.push("Sports".to_string());
+        assert!(filters.check(&buy, &sports, NOW).is_err());
+
+        // Exits are never filtered
+        let mut sell = buy.clone();
+        sell.side = TradeSide::SELL;
+        assert!(filters.check(&sell, &sports, NOW).is_ok());
+    }
+
+    #[test]
+    fn test_wallet_override_replaces_defaults() {
+        let mut wallet_rules = HashMap::new();
+        wallet_rules.insert("0xsports".to_string(), FilterRules {
+            allow_keywords: Some("lakers|celtics".to_string()),
+            ..Default::default()
+        });
+        let filters = filters(wallet_rules);
+        let sports = Market { tags: vec!["sports".to_string()], ..market("Will the Lakers win?", 0.5, 30) };
+
+        assert!(filters.check(&trade("0xSPORTS", Outcome::Yes), &sports, NOW).is_ok());
+        assert!(filters.check(&trade("0xsports", Outcome::Yes), &market("Will Alice win?", 0.5, 30), NOW).is_err());
+        assert!(filters.check(&trade("0xwhale", Outcome::Yes), &sports, NOW).is_err());
+    }
+
+    #[test]
+    fn test_chain_composes_custom_filters() {
+        struct NoNegRisk;
+        impl MarketFilter for NoNegRisk {
+            fn check(&self, _trade: &Trade, market: &Market, _now: i64) -> Result<()> {
+                if market.neg_risk {
+                    bail!("Neg-risk markets are off limits");
+                }
+                Ok(())
+            }
+        }
+
+        let chain = FilterChain::new().with(PriceBand { min: 0.1, max: 0.9 }).with(NoNegRisk);
+        let m = Market { neg_risk: true, ..market("Will Alice win?", 0.5, 30) };
+        let err = chain.check(&trade("0xwhale", Outcome::Yes), &m, NOW).unwrap_err();
+        assert!(err.to_string().contains("Neg-risk"));
+    }
+}
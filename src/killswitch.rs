@@ -0,0 +1,133 @@
+use crate::types::Config;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum KillReason {
+    DailyLoss { pnl: f64, limit: f64 },
+    Drawdown { peak: f64, equity: f64, pct: f64, limit: f64 },
+}
+
+impl fmt::Display for KillReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KillReason::DailyLoss { pnl, limit } =>
+                write!(f, "Daily PnL ${:.2} breaches the ${:.2} loss limit", pnl, limit),
+            KillReason::Drawdown { peak, equity, pct, limit } =>
+                write!(f, "Drawdown {:.1}% from peak ${:.2} (now ${:.2}) breaches {:.1}%",
+                    pct * 100.0, peak, equity, limit * 100.0),
+        }
+    }
+}
+
+// Hard stop on losses, counting marked-to-market positions as well as
+// realized PnL. Unlike the circuit breaker it never recovers on its own:
+// a daily-loss kill lifts at the next daily reset, a drawdown kill only
+// when reset by hand. Its limits are separate from the breaker's, and like
+// the breaker's they engage once reached, not only once exceeded.
+#[derive(Debug, Clone)]
+pub struct KillSwitch {
+    config: Config,
+    equity_peak: f64,
+    reason: Option<KillReason>,
+}
+
+impl KillSwitch {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            equity_peak: 0.0,
+            reason: None,
+        }
+    }
+
+    pub fn is_engaged(&self) -> bool {
+        self.reason.is_some()
+    }
+
+    pub fn reason(&self) -> Option<&KillReason> {
+        self.reason.as_ref()
+    }
+
+    // `pnl_today` is realized plus the change in unrealized since the daily
+    // reset; `equity` is cash plus marked position value, when cash is known.
+    // Returns the reason if this update engaged the switch.
+    pub fn update(&mut self, pnl_today: f64, equity: Option<f64>) -> Option<KillReason> {
+        if let Some(equity) = equity {
+            self.equity_peak = self.equity_peak.max(equity);
+        }
+        if self.is_engaged() {
+            return None;
+        }
+
+        let reason = if -pnl_today >= self.config.max_daily_loss {
+            Some(KillReason::DailyLoss { pnl: pnl_today, limit: self.config.max_daily_loss })
+        } else {
+            equity.filter(|_| self.equity_peak > 0.0).and_then(|equity| {
+                let pct = (self.equity_peak - equity) / self.equity_peak;
+                (pct >= self.config.max_drawdown_pct).then_some(KillReason::Drawdown {
+                    peak: self.equity_peak,
+                    equity,
+                    pct,
+                    limit: self.config.max_drawdown_pct,
+                })
+            })
+        };
+
+        if let Some(reason) = &reason {
+            tracing::error!("KILL SWITCH ENGAGED: {}", reason);
+            self.reason = Some(reason.clone());
+        }
+        reason
+    }
+
+    // A new trading day: daily losses start over, drawdown does not
+    pub fn roll_day(&mut self) {
+        if matches!(self.reason, Some(KillReason::DailyLoss { .. })) {
+            tracing::info!("Kill switch released by daily reset");
+            self.reason = None;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.reason = None;
+        self.equity_peak = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        Config { max_daily_loss: 100.0, max_drawdown_pct: 0.2, ..Default::default() }
+    }
+
+    #[test]
+    fn test_daily_loss_latches_until_next_day() {
+        let mut ks = KillSwitch::new(config());
+        assert!(ks.update(-60.0, None).is_none());
+        assert!(matches!(ks.update(-100.0, None), Some(KillReason::DailyLoss { .. })));
+
+        // Marks recovering intraday don't release it
+        ks.update(-10.0, None);
+        assert!(ks.is_engaged());
+
+        ks.roll_day();
+        assert!(!ks.is_engaged());
+    }
+
+    #[test]
+    fn test_drawdown_survives_day_roll() {
+        let mut ks = KillSwitch::new(config());
+        ks.update(0.0, Some(1000.0));
+        ks.update(0.0, Some(1250.0));
+        assert!(ks.update(0.0, Some(1010.0)).is_none());
+        assert!(matches!(ks.update(0.0, Some(990.0)), Some(KillReason::Drawdown { .. })));
+
+        ks.roll_day();
+        assert!(ks.is_engaged());
+        ks.reset();
+        assert!(!ks.is_engaged());
+    }
+}
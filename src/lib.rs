@@ -1,7 +1,25 @@
 pub mod types;
+pub mod aggregation;
+pub mod analytics;
+pub mod error;
 pub mod config;
+pub mod consensus;
+pub mod discovery;
 pub mod api;
+pub mod book;
 pub mod watcher;
 pub mod sizing;
 pub mod risk;
 pub mod executor;
+pub mod orders;
+pub mod pipeline;
+pub mod queue;
+pub mod ratelimit;
+pub mod exposure;
+pub mod filters;
+pub mod breaker;
+pub mod cache;
+pub mod killswitch;
+pub mod limits;
+pub mod scheduler;
+pub mod signals;
@@ -0,0 +1,143 @@
+use crate::types::{Config, Trade, TradeSide};
+use anyhow::{Result, bail};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+const MINUTE: Duration = Duration::from_secs(60);
+const HOUR: Duration = Duration::from_secs(3600);
+
+// Sliding-window limits on how fast we copy, on top of the calendar-day
+// volume cap. Copies are counted when they are let through; volume is
+// counted from actual fills.
+#[derive(Debug)]
+pub struct RateLimits {
+    config: Config,
+    copies: VecDeque<(Instant, String)>,  // (when, lowercase whale wallet) over the last minute
+    volume: VecDeque<(Instant, f64)>,  // (when, USD) over the last hour
+    last_copy: HashMap<(String, TradeSide), Instant>,  // Per market and direction
+}
+
+impl RateLimits {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            copies: VecDeque::new(),
+            volume: VecDeque::new(),
+            last_copy: HashMap::new(),
+        }
+    }
+
+    pub fn check(&mut self, now: Instant, trade: &Trade, size_usd: f64) -> Result<()> {
+        self.expire(now);
+
+        let hourly: f64 = self.volume.iter().map(|(_, usd)| usd).sum();
+        if hourly + size_usd > self.config.max_hourly_volume {
+            bail!("Hourly volume limit exceeded: ${:.2} + ${:.2} > ${:.2}",
+                hourly, size_usd, self.config.max_hourly_volume);
+        }
+
+        if self.copies.len() >= self.config.max_trades_per_minute as usize {
+            bail!("Trade rate limit exceeded: {} copies in the last minute", self.copies.len());
+        }
+
+        let wallet = trade.wallet.to_lowercase();
+        let from_wallet = self.copies.iter().filter(|(_, w)| *w == wallet).count();
+        if from_wallet >= self.config.max_wallet_trades_per_minute as usize {
+            bail!("Trade rate limit for {} exceeded: {} copies in the last minute",
+                trade.wallet, from_wallet);
+        }
+
+        let key = (trade.market_id.clone(), trade.side.clone());
+        if let Some(last) = self.last_copy.get(&key) {
+            let cooldown = Duration::from_secs(self.config.market_cooldown_secs);
+            let since = now.duration_since(*last);
+            if since < cooldown {
+                bail!("Market {} cooling down: copied {}s ago, cooldown {}s",
+                    trade.market_id, since.as_secs(), cooldown.as_secs());
+            }
+        }
+
+        Ok(())
+    }
+
+    // A copy passed every check and is about to be placed
+    pub fn record_copy(&mut self, now: Instant, trade: &Trade) {
+        self.copies.push_back((now, trade.wallet.to_lowercase()));
+        self.last_copy.insert((trade.market_id.clone(), trade.side.clone()), now);
+    }
+
+    pub fn record_volume(&mut self, now: Instant, usd: f64) {
+        self.volume.push_back((now, usd));
+    }
+
+    fn expire(&mut self, now: Instant) {
+        while self.copies.front().is_some_and(|(t, _)| now.duration_since(*t) >= MINUTE) {
+            self.copies.pop_front();
+        }
+        while self.volume.front().is_some_and(|(t, _)| now.duration_since(*t) >= HOUR) {
+            self.volume.pop_front();
+        }
+        let cooldown = Duration::from_secs(self.config.market_cooldown_secs);
+        self.last_copy.retain(|_, t| now.duration_since(*t) < cooldown);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::fixtures;
+
+    fn trade(wallet: &str, market_id: &str) -> Trade {
+        Trade {
+            wallet: wallet.to_string(),
+            shares: 10.0,
+            ..fixtures::trade(market_id)
+        }
+    }
+
+    fn limits() -> RateLimits {
+        RateLimits::new(Config {
+            max_hourly_volume: 100.0,
+            max_trades_per_minute: 3,
+            max_wallet_trades_per_minute: 2,
+            market_cooldown_secs: 30,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_burst_from_one_whale_is_throttled() {
+        let mut limits = limits();
+        let t0 = Instant::now();
+
+        // The same market again is blocked by the cooldown
+        assert!(limits.check(t0, &trade("0xa", "m1"), 5.0).is_ok());
+        limits.record_copy(t0, &trade("0xa", "m1"));
+        assert!(limits.check(t0, &trade("0xa", "m1"), 5.0).is_err());
+
+        // Other markets go through until the per-wallet cap, however the
+        // address is cased
+        limits.record_copy(t0, &trade("0xA", "m2"));
+        assert!(limits.check(t0, &trade("0xa", "m3"), 5.0).is_err());
+
+        // Another whale hits the global cap
+        limits.record_copy(t0, &trade("0xb", "m3"));
+        assert!(limits.check(t0, &trade("0xc", "m4"), 5.0).is_err());
+
+        // A minute later the window has rolled and the cooldown is over
+        let t1 = t0 + MINUTE;
+        assert!(limits.check(t1, &trade("0xa", "m1"), 5.0).is_ok());
+    }
+
+    #[test]
+    fn test_hourly_volume_slides() {
+        let mut limits = limits();
+        let t0 = Instant::now();
+        limits.record_volume(t0, 60.0);
+        limits.record_volume(t0 + Duration::from_secs(1800), 30.0);
+
+        assert!(limits.check(t0 + Duration::from_secs(1800), &trade("0xa", "m1"), 20.0).is_err());
+        // The first fill has dropped out of the window
+        assert!(limits.check(t0 + HOUR, &trade("0xa", "m1"), 20.0).is_ok());
+    }
+}
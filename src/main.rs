@@ -1,9 +1,8 @@
 use anyhow::Result;
-use chrono::Timelike;
 use std::sync::Arc;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use polymarket_copy_bot::{api, config, executor, risk, sizing, types, watcher};
+use polymarket_copy_bot::{aggregation, analytics, api, book, cache, config, executor, exposure, pipeline, risk, scheduler, sizing, types, watcher};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -28,139 +27,223 @@ async fn main() -> Result<()> {
     tracing::info!("   Your wallet: {}", &config.your_wallet[..10]);
     
     // Initialize components
-    let api = api::PolymarketApi::new(config.polymarket_api.clone());
+    let api = api::PolymarketApi::new(&config)?;
+    let cached_api = cache::CachedApi::new(api.clone(), &config);
     let watcher = watcher::WalletWatcher::new(
         config.ws_url.clone(),
         config.wallets_to_track.clone(),
     );
-    let sizer = sizing::PositionSizer::new(config.clone());
+    let sizer = Arc::new(sizing::PositionSizer::new(config.clone()));
     let risk = Arc::new(risk::RiskManager::new(config.clone()));
-    let executor = executor::TradeExecutor::new(api.clone(), config.clone());
+    let mut executor = executor::TradeExecutor::new(api.clone(), config.clone());
+    if config.order_book_mirror {
+        let books = Arc::new(book::OrderBooks::new());
+        let idle_timeout = tokio::time::Duration::from_secs(config.book_idle_timeout_secs);
+        tokio::spawn(Arc::clone(&books).run(config.market_ws_url.clone(), idle_timeout));
+        executor = executor.with_order_books(books);
+    }
+    let executor = Arc::new(executor);
+    let pipeline = Arc::new(pipeline::TradePipeline::new(
+        config.clone(),
+        cached_api.clone(),
+        Arc::clone(&sizer),
+        Arc::clone(&risk),
+        Arc::clone(&executor),
+    )?);
     
     tracing::info!("✅ Components initialized");
     
+    // Pay for TCP and TLS setup now rather than on the first copy
+    api.warm_up(config.api_prewarm_connections).await;
+    
+    // Poll resting orders, cancel stale ones, and pull everything if the breaker trips
+    tokio::spawn(executor.orders().run(Arc::clone(&risk)));
+    
+    // Keep position marks fresh and flatten if the kill switch engages
+    tokio::spawn(run_kill_switch(cached_api.clone(), Arc::clone(&executor), Arc::clone(&risk), config.clone()));
+    
+    // Our balance moves with our fills; check it against the exchange now and then
+    tokio::spawn(run_balance_reconciliation(cached_api.clone(), Arc::clone(&risk), config.clone()));
+    
+    // Re-score tracked wallets on their recent history
+    if config.score_sizing || config.auto_disable_wallets {
+        tokio::spawn(run_whale_scoring(api.clone(), Arc::clone(&sizer), Arc::clone(&risk), config.clone()));
+    }
+    
     // Start watching wallets
     let trade_rx = watcher.start().await?;
     tracing::info!("✅ WebSocket watchers started");
     
-    // Reset daily stats at midnight
+    // One whale order often arrives as a burst of fills; copy it once
+    if config.fill_aggregation_ms > 0 {
+        aggregation::FillAggregator::new(std::time::Duration::from_millis(config.fill_aggregation_ms))
+            .start(trade_rx, pipeline.queue());
+    } else {
+        pipeline.queue().forward_from(trade_rx);
+    }
+    
+    // Reset daily stats at the configured local time, catching up on startup
+    let reset_scheduler = scheduler::DailyResetScheduler::new(
+        Arc::new(scheduler::SystemClock),
+        chrono::NaiveTime::parse_from_str(&config.daily_reset_time, "%H:%M")?,
+        config.daily_reset_timezone.parse().map_err(|e| anyhow::anyhow!("{}", e))?,
+        Some(config.reset_state_path.clone()).filter(|p| !p.is_empty()).map(Into::into),
+    );
     let risk_clone = Arc::clone(&risk);
-    tokio::spawn(async move {
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(3600));
-        loop {
-            interval.tick().await;
-            let now = chrono::Utc::now();
-            if now.hour() == 0 && now.minute() < 1 {
-                risk_clone.reset_daily_stats();
-            }
-        }
-    });
+    tokio::spawn(reset_scheduler.run(move || risk_clone.reset_daily_stats()));
     
-    // Main trading loop
+    // Main trading pipeline
     tracing::info!("🎯 Bot is now live and monitoring trades...");
     
-    while let Ok(whale_trade) = trade_rx.recv().await {
-        tracing::info!("📊 Detected trade from {}: {} {:.2} shares @ ${:.4}",
-            &whale_trade.wallet[..10],
-            match whale_trade.side {
-                types::TradeSide::BUY => "BUY",
-                types::TradeSide::SELL => "SELL",
-            },
-            whale_trade.shares,
-            whale_trade.price
-        );
-        
-        // Verify whale
-        if !risk.is_whale_verified(&whale_trade.wallet) {
-            tracing::warn!("⚠️  Unverified wallet, skipping");
-            continue;
-        }
-        
-        // Get market info
-        let market = match api.get_market(&whale_trade.market_id).await {
-            Ok(m) => m,
-            Err(e) => {
-                tracing::error!("Failed to fetch market: {}", e);
-                risk.record_error(&format!("Market fetch failed: {}", e));
-                continue;
+    pipeline.run().await;
+    
+    tracing::info!("Bot stopped");
+    Ok(())
+}
+
+async fn run_kill_switch(
+    api: cache::CachedApi,
+    executor: Arc<executor::TradeExecutor>,
+    risk: Arc<risk::RiskManager>,
+    config: types::Config,
+) {
+    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(config.mark_interval_secs));
+    let mut killed = risk.kill_signal();
+    
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let held: Vec<String> = risk.exposure().positions().map(|(id, _)| id.clone()).collect();
+                for market_id in held {
+                    // Local books give marks without a round trip; REST until they're in sync
+                    let books = executor.order_books();
+                    if let Some(marked) = books.and_then(|b| b.mark(&market_id)) {
+                        risk.mark_market(&marked);
+                        continue;
+                    }
+                    match api.refresh_market(&market_id).await {
+                        Ok(market) => {
+                            if let Some(books) = books {
+                                books.watch_market(&market);
+                            }
+                            risk.mark_market(&market);
+                        }
+                        Err(e) => tracing::warn!("Failed to mark {}: {}", market_id, e),
+                    }
+                }
             }
-        };
-        
-        tracing::info!("   Market: {}", market.question);
-        tracing::info!("   Liquidity: ${:.2}", market.liquidity);
-        
-        // Get balances
-        let your_balance = match api.get_balance(&config.your_wallet).await {
+            changed = killed.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                if *killed.borrow_and_update() && config.flatten_on_kill {
+                    flatten_positions(&api, &executor, &risk).await;
+                }
+            }
+        }
+    }
+}
+
+async fn run_balance_reconciliation(
+    api: cache::CachedApi,
+    risk: Arc<risk::RiskManager>,
+    config: types::Config,
+) {
+    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(config.balance_reconcile_secs));
+    
+    loop {
+        interval.tick().await;
+        let balance = match api.fetch_balance(&config.your_wallet).await {
             Ok(b) => b,
             Err(e) => {
-                tracing::error!("Failed to fetch your balance: {}", e);
-                risk.record_error(&format!("Balance fetch failed: {}", e));
+                tracing::warn!("Balance reconciliation failed: {}", e);
                 continue;
             }
         };
         
-        let whale_balance = match api.get_balance(&whale_trade.wallet).await {
-            Ok(b) => b,
-            Err(e) => {
-                tracing::error!("Failed to fetch whale balance: {}", e);
-                1000000.0 // Default to large number if we can't fetch
+        // Drift means fees, settlements or transfers we didn't see as fills
+        if let Some(tracked) = risk.cash() {
+            if (balance - tracked).abs() >= 0.01 {
+                tracing::info!("💱 Balance drift: tracked ${:.2}, exchange ${:.2}", tracked, balance);
             }
-        };
+        }
+        risk.record_balance(balance);
+        
+        for (resource, stats) in api.stats() {
+            tracing::debug!("Cache {}: {} entries, {} hits, {} misses", resource, stats.len, stats.hits, stats.misses);
+        }
+    }
+}
+
+async fn run_whale_scoring(
+    api: api::PolymarketApi,
+    sizer: Arc<sizing::PositionSizer>,
+    risk: Arc<risk::RiskManager>,
+    config: types::Config,
+) {
+    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(config.score_interval_secs));
+    let mut scorer = analytics::WhaleScorer::new(&api);
+    
+    loop {
+        interval.tick().await;
+        let since = chrono::Utc::now().timestamp() - config.score_lookback_days as i64 * 86400;
         
-        // Calculate position size
-        let size_usd = match sizer.calculate_size(&whale_trade, your_balance, whale_balance).await {
-            Ok(s) => s,
+        for wallet in &config.wallets_to_track {
+            let stats = match scorer.score_wallet(wallet, since).await {
+                Ok(s) => s,
+                Err(e) => {
+                    tracing::warn!("Whale scoring failed: {:#}", e);
+                    continue;
+                }
+            };
+            tracing::info!("📐 {}: {} closed, win {:.0}%, ROI {:+.1}%",
+                wallet.get(..10).unwrap_or(wallet), stats.closed_positions, stats.win_rate * 100.0, stats.roi * 100.0);
+            
+            if config.score_sizing {
+                sizer.set_confidence(wallet, stats.confidence(config.min_scored_positions));
+            }
+            if config.auto_disable_wallets {
+                match stats.underperformance(config.min_scored_positions, config.min_wallet_roi) {
+                    Some(reason) => risk.disable_wallet(wallet, &reason),
+                    None => risk.enable_wallet(wallet),
+                }
+            }
+        }
+    }
+}
+
+async fn flatten_positions(
+    api: &cache::CachedApi,
+    executor: &executor::TradeExecutor,
+    risk: &risk::RiskManager,
+) {
+    tracing::error!("🛑 Kill switch engaged, flattening all positions");
+    
+    let positions: Vec<(String, exposure::MarketPosition)> = risk.exposure()
+        .positions()
+        .map(|(id, p)| (id.clone(), p.clone()))
+        .collect();
+    
+    for (market_id, position) in positions {
+        let market = match api.refresh_market(&market_id).await {
+            Ok(m) => m,
             Err(e) => {
-                tracing::error!("Failed to calculate size: {}", e);
-                risk.record_error(&format!("Sizing failed: {}", e));
+                tracing::error!("Cannot flatten {}: market fetch failed: {}", market_id, e);
                 continue;
             }
         };
         
-        let shares = sizer.shares_from_usd(size_usd, whale_trade.price);
-        
-        tracing::info!("   Your size: ${:.2} ({:.2} shares)", size_usd, shares);
-        
-        // Risk checks
-        if let Err(e) = risk.check_can_trade(&whale_trade, &market, size_usd) {
-            tracing::error!("❌ Risk check failed: {}", e);
-            continue;
-        }
-        
-        tracing::info!("✅ Risk checks passed");
-        
-        // Execute trade
-        tracing::info!("🔄 Executing mirror trade...");
-        
-        match executor.execute_trade(&whale_trade, shares).await {
-            Ok(resp) => {
-                tracing::info!("✅ Trade executed successfully!");
-                tracing::info!("   Order ID: {}", resp.order_id);
-                tracing::info!("   Filled: {:.2} shares @ ${:.4}", resp.filled_shares, resp.avg_fill_price);
-                tracing::info!("   Total: ${:.2}", resp.filled_shares * resp.avg_fill_price);
-                
-                risk.record_trade(&whale_trade, size_usd);
+        for outcome in [types::Outcome::Yes, types::Outcome::No] {
+            let shares = position.outcome(outcome).shares;
+            if shares <= 0.0 {
+                continue;
             }
-            Err(e) => {
-                tracing::error!("❌ Trade execution failed: {}", e);
-                risk.record_error(&format!("Execution failed: {}", e));
+            
+            match executor.close_position(&market, outcome, shares, types::TradeSide::BUY).await {
+                // Book the exit against our own position
+                Ok(resp) => risk.record_fill(&executor.exit_trade(&market, outcome, shares, types::TradeSide::BUY), &resp),
+                Err(e) => tracing::error!("Failed to close {:?} on {}: {}", outcome, market.id, e),
             }
         }
-        
-        // Show circuit breaker status
-        let cb_state = risk.get_state();
-        tracing::info!("📈 Daily stats: {} trades, ${:.2} volume", 
-            cb_state.total_trades_today, 
-            cb_state.total_volume_today
-        );
-        
-        if cb_state.is_tripped {
-            tracing::error!("⚠️  CIRCUIT BREAKER TRIPPED - Bot paused!");
-        }
-        
-        tracing::info!("---");
     }
-    
-    tracing::info!("Bot stopped");
-    Ok(())
 }
@@ -0,0 +1,303 @@
+use crate::api::Exchange;
+use crate::risk::RiskManager;
+use crate::types::{Config, OrderRequest, OrderResponse, OrderStatus, Trade};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct TrackedOrder {
+    pub trade: Trade,
+    pub request: OrderRequest,
+    pub order_id: String,
+    pub status: OrderStatus,
+    pub filled_shares: f64,
+    pub avg_fill_price: f64,
+    pub fees: f64,
+    pub placed_at: i64,
+}
+
+// Tracks orders left resting on the book (GTD sells, partially filled limits)
+// until they fill, expire or we cancel them.
+pub struct OrderManager {
+    api: Arc<dyn Exchange>,
+    config: Config,
+    open_orders: Mutex<HashMap<String, TrackedOrder>>,
+    // Fills that happened after placement, waiting to be booked by the risk manager
+    pending_fills: Mutex<Vec<(Trade, OrderResponse)>>,
+}
+
+impl OrderManager {
+    pub fn new(api: Arc<dyn Exchange>, config: Config) -> Self {
+        Self {
+            api,
+            config,
+            open_orders: Mutex::new(HashMap::new()),
+            pending_fills: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn track(&self, trade: &Trade, request: &OrderRequest, resp: &OrderResponse) {
+        let order = TrackedOrder {
+            trade: trade.clone(),
+            request: request.clone(),
+            order_id: resp.order_id.clone(),
+            status: resp.status.clone(),
+            filled_shares: resp.filled_shares,
+            avg_fill_price: resp.avg_fill_price,
+            fees: resp.fees,
+            placed_at: chrono::Utc::now().timestamp(),
+        };
+
+        tracing::info!("Tracking resting order {} ({} {:.2} shares on {})",
+            order.order_id, order.status, request.shares, request.market_id);
+
+        self.open_orders.lock().unwrap().insert(resp.order_id.clone(), order);
+    }
+
+    pub fn open_orders(&self) -> Vec<TrackedOrder> {
+        self.open_orders.lock().unwrap().values().cloned().collect()
+    }
+
+    // Refreshes every tracked order; new fills are queued for take_fills()
+    pub async fn poll_open_orders(&self) {
+        for order in self.open_orders() {
+            match self.api.get_order(&order.order_id, &self.config.private_key).await {
+                Ok(latest) => self.apply_update(&latest),
+                Err(e) => tracing::warn!("Failed to poll order {}: {}", order.order_id, e),
+            }
+        }
+    }
+
+    pub fn take_fills(&self) -> Vec<(Trade, OrderResponse)> {
+        std::mem::take(&mut *self.pending_fills.lock().unwrap())
+    }
+
+    fn apply_update(&self, latest: &OrderResponse) {
+        let mut orders = self.open_orders.lock().unwrap();
+        let Some(order) = orders.get_mut(&latest.order_id) else {
+            return;
+        };
+
+        // The exchange reports cumulative fills; book only the increment
+        let delta_shares = latest.filled_shares - order.filled_shares;
+        if delta_shares > 0.0 {
+            let delta_cost = latest.filled_shares * latest.avg_fill_price
+                - order.filled_shares * order.avg_fill_price;
+            let fill = OrderResponse {
+                order_id: latest.order_id.clone(),
+                status: latest.status.clone(),
+                filled_shares: delta_shares,
+                avg_fill_price: delta_cost / delta_shares,
+                fees: (latest.fees - order.fees).max(0.0),
+            };
+
+            tracing::info!("Order {} filled {:.2} -> {:.2} shares",
+                order.order_id, order.filled_shares, latest.filled_shares);
+            self.pending_fills.lock().unwrap().push((order.trade.clone(), fill));
+        }
+
+        match latest.status {
+            OrderStatus::Open | OrderStatus::PartiallyFilled | OrderStatus::Pending | OrderStatus::Unknown => {
+                order.status = latest.status.clone();
+                order.filled_shares = latest.filled_shares;
+                order.avg_fill_price = latest.avg_fill_price;
+                order.fees = latest.fees;
+            }
+            OrderStatus::Filled | OrderStatus::Cancelled | OrderStatus::Rejected | OrderStatus::Expired => {
+                orders.remove(&latest.order_id);
+                tracing::info!("Order {} is {}, no longer tracked", latest.order_id, latest.status);
+            }
+        }
+    }
+
+    pub async fn cancel_order(&self, order_id: &str) -> anyhow::Result<OrderResponse> {
+        let resp = self.api.cancel_order(order_id, &self.config.private_key).await?;
+        self.apply_update(&resp);
+        Ok(resp)
+    }
+
+    pub async fn cancel_stale(&self, now: i64) -> usize {
+        let ttl = self.config.order_ttl_secs as i64;
+        let stale: Vec<String> = self.open_orders()
+            .into_iter()
+            .filter(|o| now - o.placed_at >= ttl)
+            .map(|o| o.order_id)
+            .collect();
+
+        let mut cancelled = 0;
+        for order_id in stale {
+            match self.cancel_order(&order_id).await {
+                Ok(_) => {
+                    tracing::info!("Cancelled stale order {} (older than {}s)", order_id, ttl);
+                    cancelled += 1;
+                }
+                Err(e) => tracing::warn!("Failed to cancel stale order {}: {}", order_id, e),
+            }
+        }
+
+        cancelled
+    }
+
+    // Uses the exchange-wide cancel so orders from a previous run are swept up too
+    pub async fn cancel_all(&self) -> anyhow::Result<usize> {
+        let cancelled = self.api.cancel_all(&self.config.private_key).await?;
+
+        // One last look at each order so fills since the previous poll get booked
+        let orders = self.open_orders();
+        for order in &orders {
+            match self.api.get_order(&order.order_id, &self.config.private_key).await {
+                Ok(latest) => self.apply_update(&latest),
+                Err(e) => tracing::warn!("Final poll of order {} failed, recent fills may be unbooked: {}",
+                    order.order_id, e),
+            }
+        }
+        self.open_orders.lock().unwrap().clear();
+        let tracked = orders.len();
+
+        tracing::warn!("Cancelled all resting orders ({} tracked, {} reported by exchange)",
+            tracked, cancelled.len());
+
+        Ok(cancelled.len().max(tracked))
+    }
+
+    fn book_fills(&self, risk: &RiskManager) {
+        for (trade, fill) in self.take_fills() {
+            risk.record_fill(&trade, &fill);
+        }
+    }
+
+    pub async fn run(self: Arc<Self>, risk: Arc<RiskManager>) {
+        let mut interval = tokio::time::interval(Duration::from_millis(self.config.order_poll_interval_ms));
+        let mut breaker_tripped = risk.breaker_signal();
+        // Act on a breaker that tripped before this task started
+        breaker_tripped.mark_changed();
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    self.poll_open_orders().await;
+                    self.cancel_stale(chrono::Utc::now().timestamp()).await;
+                    self.book_fills(&risk);
+                }
+                changed = breaker_tripped.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                    if *breaker_tripped.borrow_and_update() {
+                        tracing::error!("Circuit breaker tripped, cancelling all resting orders");
+                        if let Err(e) = self.cancel_all().await {
+                            tracing::error!("Cancel-all failed: {}", e);
+                        }
+                        self.book_fills(&risk);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::{fixtures, response, MockExchange};
+    use crate::types::{OrderType, Outcome, TradeSide};
+
+    fn whale_sell() -> Trade {
+        Trade {
+            side: TradeSide::SELL,
+            shares: 50.0,
+            price: 0.6,
+            ..fixtures::trade("market1")
+        }
+    }
+
+    fn gtd_sell() -> OrderRequest {
+        OrderRequest {
+            client_order_id: "c1".to_string(),
+            market_id: "market1".to_string(),
+            token_id: "tok-yes".to_string(),
+            outcome: Outcome::Yes,
+            side: TradeSide::SELL,
+            shares: 10.0,
+            price: Some(0.6),
+            order_type: OrderType::GTD,
+            expiration: Some(0),
+            neg_risk: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_poll_tracks_fills_until_terminal() {
+        let exchange = MockExchange::default();
+        exchange.on_get_order(Ok(response("o1", OrderStatus::PartiallyFilled, 4.0, 0.6)));
+        exchange.on_get_order(Ok(response("o1", OrderStatus::Filled, 10.0, 0.6)));
+
+        let manager = OrderManager::new(Arc::new(exchange.clone()), Config::default());
+        manager.track(&whale_sell(), &gtd_sell(), &response("o1", OrderStatus::Open, 0.0, 0.0));
+
+        manager.poll_open_orders().await;
+        assert_eq!(manager.open_orders()[0].filled_shares, 4.0);
+
+        manager.poll_open_orders().await;
+        assert!(manager.open_orders().is_empty());
+
+        // Each poll queues only the increment over the previous cumulative fill
+        let fills: Vec<f64> = manager.take_fills().iter().map(|(_, f)| f.filled_shares).collect();
+        assert_eq!(fills, vec![4.0, 6.0]);
+        assert!(manager.take_fills().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stale_orders() {
+        let exchange = MockExchange::default();
+        let config = Config { order_ttl_secs: 60, ..Default::default() };
+        let manager = OrderManager::new(Arc::new(exchange.clone()), config);
+        manager.track(&whale_sell(), &gtd_sell(), &response("o1", OrderStatus::Open, 0.0, 0.0));
+
+        let placed_at = manager.open_orders()[0].placed_at;
+        assert_eq!(manager.cancel_stale(placed_at + 30).await, 0);
+        assert_eq!(manager.cancel_stale(placed_at + 60).await, 1);
+        assert_eq!(exchange.cancelled(), vec!["o1"]);
+        assert!(manager.open_orders().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_all_books_fills_since_last_poll() {
+        let exchange = MockExchange::default();
+        exchange.on_get_order(Ok(response("o1", OrderStatus::Cancelled, 4.0, 0.6)));
+
+        let manager = OrderManager::new(Arc::new(exchange.clone()), Config::default());
+        manager.track(&whale_sell(), &gtd_sell(), &response("o1", OrderStatus::Open, 0.0, 0.0));
+        manager.track(&whale_sell(), &gtd_sell(), &response("o2", OrderStatus::Open, 0.0, 0.0));
+
+        // o2's final poll fails; it's dropped all the same
+        assert_eq!(manager.cancel_all().await.unwrap(), 2);
+        assert!(manager.open_orders().is_empty());
+
+        let fills: Vec<f64> = manager.take_fills().iter().map(|(_, f)| f.filled_shares).collect();
+        assert_eq!(fills, vec![4.0]);
+    }
+
+    #[tokio::test]
+    async fn test_breaker_trip_cancels_everything() {
+        let exchange = MockExchange::default();
+        let manager = Arc::new(OrderManager::new(Arc::new(exchange.clone()), Config::default()));
+        manager.track(&whale_sell(), &gtd_sell(), &response("o1", OrderStatus::Open, 0.0, 0.0));
+
+        let risk = Arc::new(RiskManager::new(Config { cb_consecutive_trigger: 1, ..Default::default() }));
+        let handle = tokio::spawn(Arc::clone(&manager).run(Arc::clone(&risk)));
+
+        risk.record_error("exchange down");
+        for _ in 0..100 {
+            if exchange.cancel_all_calls() > 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        handle.abort();
+
+        assert_eq!(exchange.cancel_all_calls(), 1);
+        assert!(manager.open_orders().is_empty());
+    }
+}
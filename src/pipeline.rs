@@ -0,0 +1,536 @@
+use crate::cache::CachedApi;
+use crate::consensus::ConsensusAggregator;
+use crate::executor::TradeExecutor;
+use crate::filters::MarketFilters;
+use crate::queue::SignalQueue;
+use crate::risk::RiskManager;
+use crate::signals::SignalGuard;
+use crate::sizing::PositionSizer;
+use crate::types::{Config, Trade, TradeSide};
+use anyhow::Result;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+#[derive(Debug, Default)]
+pub struct PipelineMetrics {
+    in_flight: AtomicUsize,
+    processed: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PipelineStats {
+    pub in_flight: usize,
+    pub processed: u64,
+}
+
+impl PipelineMetrics {
+    pub fn snapshot(&self) -> PipelineStats {
+        PipelineStats {
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            processed: self.processed.load(Ordering::Relaxed),
+        }
+    }
+
+    fn on_started(&self) {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_finished(&self) {
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+        self.processed.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// Runs trades concurrently, up to `workers` at a time. A signal is only
+// taken off the queue once a worker is free, so a backlog stays in the queue
+// where its overflow policy applies, and the queue never hands out a second
+// trade for a market until the first is released.
+pub struct WorkerPool {
+    workers: usize,
+    metrics: Arc<PipelineMetrics>,
+}
+
+impl WorkerPool {
+    pub fn new(workers: usize, metrics: Arc<PipelineMetrics>) -> Self {
+        Self { workers: workers.max(1), metrics }
+    }
+
+    // Returns once the queue is closed and every trade has been handled
+    pub async fn run<F, Fut>(self, input: SignalQueue, handler: F)
+    where
+        F: Fn(Trade) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        let permits = Arc::new(Semaphore::new(self.workers));
+
+        loop {
+            let permit = Arc::clone(&permits).acquire_owned().await.expect("worker semaphore closed");
+            let Some(trade) = input.recv().await else {
+                break;
+            };
+
+            let handler = Arc::clone(&handler);
+            let metrics = Arc::clone(&self.metrics);
+            let queue = input.clone();
+            tokio::spawn(async move {
+                let market_id = trade.market_id.clone();
+                metrics.on_started();
+                // A panicking trade mustn't wedge its market
+                if let Err(e) = tokio::spawn(handler(trade)).await {
+                    tracing::error!("Trade handler for {} failed: {}", market_id, e);
+                }
+                metrics.on_finished();
+                queue.release(&market_id);
+                drop(permit);
+            });
+        }
+
+        // Let in-flight trades finish
+        let _ = permits.acquire_many(self.workers as u32).await;
+    }
+}
+
+// Everything that happens to a whale trade between the watcher and the exchange
+pub struct TradePipeline {
+    config: Config,
+    api: CachedApi,
+    filters: MarketFilters,
+    signal_guard: SignalGuard,
+    consensus: Option<Mutex<ConsensusAggregator>>,
+    sizer: Arc<PositionSizer>,
+    risk: Arc<RiskManager>,
+    executor: Arc<TradeExecutor>,
+    queue: SignalQueue,
+    metrics: Arc<PipelineMetrics>,
+}
+
+impl TradePipeline {
+    pub fn new(
+        config: Config,
+        api: CachedApi,
+        sizer: Arc<PositionSizer>,
+        risk: Arc<RiskManager>,
+        executor: Arc<TradeExecutor>,
+    ) -> Result<Self> {
+        // Bigger trades from wallets we rate more highly are worth more if we fall behind
+        let weights = config.clone();
+        let confidence = Arc::clone(&sizer);
+        let queue = SignalQueue::new(
+            config.signal_queue_capacity,
+            config.overflow_policy,
+            Duration::from_secs(config.max_signal_age_secs),
+            Arc::new(move |t: &Trade| {
+                t.shares * t.price * weights.wallet_weight(&t.wallet) * confidence.confidence(&t.wallet)
+            }),
+        );
+
+        Ok(Self {
+            filters: MarketFilters::new(&config)?,
+            signal_guard: SignalGuard::new(config.clone()),
+            consensus: config.consensus_enabled
+                .then(|| Mutex::new(ConsensusAggregator::new(config.clone()))),
+            config,
+            api,
+            sizer,
+            risk,
+            executor,
+            queue,
+            metrics: Arc::new(PipelineMetrics::default()),
+        })
+    }
+
+    pub fn metrics(&self) -> Arc<PipelineMetrics> {
+        Arc::clone(&self.metrics)
+    }
+
+    // Where signals go in
+    pub fn queue(&self) -> SignalQueue {
+        self.queue.clone()
+    }
+
+    pub async fn run(self: Arc<Self>) {
+        let pool = WorkerPool::new(self.config.pipeline_workers, self.metrics());
+        pool.run(self.queue(), move |trade| {
+            let pipeline = Arc::clone(&self);
+            async move { pipeline.process(trade).await }
+        }).await;
+    }
+
+    pub async fn process(&self, whale_trade: Trade) {
+        tracing::info!("📊 Detected trade from {}: {} {:.2} {:?} shares @ ${:.4}",
+            whale_trade.wallet.get(..10).unwrap_or(&whale_trade.wallet),
+            match whale_trade.side {
+                TradeSide::BUY => "BUY",
+                TradeSide::SELL => "SELL",
+            },
+            whale_trade.shares,
+            whale_trade.outcome,
+            whale_trade.price
+        );
+
+        // Verify whale
+        if !self.risk.is_whale_verified(&whale_trade.wallet) {
+            tracing::warn!("⚠️  Unverified wallet, skipping");
+            return;
+        }
+
+        // Fade wallets we track for being wrong
+        let whale_trade = if self.config.is_inverted(&whale_trade.wallet) {
+            let faded = whale_trade.inverted();
+            tracing::info!("🔁 Fading {}: {:?} @ ${:.4} -> {:?} @ ${:.4}",
+                whale_trade.wallet.get(..10).unwrap_or(&whale_trade.wallet), whale_trade.outcome, whale_trade.price, faded.outcome, faded.price);
+            faded
+        } else {
+            whale_trade
+        };
+
+        // In consensus mode a buy waits until enough whales agree
+        let (whale_trade, size_multiplier) = match &self.consensus {
+            Some(aggregator) => match aggregator.lock().unwrap().observe(whale_trade) {
+                Some(signal) => (signal.trade, signal.multiplier),
+                None => return,
+            },
+            None => (whale_trade, 1.0),
+        };
+
+        // Market and whale balance at once; ours is tracked locally once known
+        let (market, your_balance, whale_balance) = tokio::join!(
+            self.api.get_market(&whale_trade.market_id),
+            async {
+                match self.risk.cash() {
+                    Some(cash) => Ok(cash),
+                    None => self.api.fetch_balance(&self.config.your_wallet).await
+                        .map(|b| self.risk.seed_balance(b)),
+                }
+            },
+            self.api.get_balance(&whale_trade.wallet),
+        );
+
+        let market = match market {
+            Ok(m) => m,
+            Err(e) => {
+                tracing::error!("Failed to fetch market: {}", e);
+                self.risk.record_data_error(&format!("Market fetch failed: {}", e));
+                return;
+            }
+        };
+
+        self.risk.mark_market(&market);
+        
+        // Whales are active here; mirror the books for this and later signals
+        if let Some(books) = self.executor.order_books() {
+            books.watch_market(&market);
+        }
+
+        // Neg-risk exposure depends on every market in the event
+        if market.neg_risk && !self.risk.knows_event(&market.event_id) {
+            match self.api.get_event(&market.event_id).await {
+                Ok(event) => self.risk.register_event(&event),
+                Err(e) => {
+                    tracing::error!("Failed to fetch event: {}", e);
+                    self.risk.record_data_error(&format!("Event fetch failed: {}", e));
+                    return;
+                }
+            }
+        }
+
+        tracing::info!("   Market: {}", market.question);
+        tracing::info!("   {:?} price: ${:.4}", whale_trade.outcome, market.price(whale_trade.outcome));
+        tracing::info!("   Liquidity: ${:.2}", market.liquidity);
+
+        // Market filters
+        if let Err(e) = self.filters.check(&whale_trade, &market, chrono::Utc::now().timestamp()) {
+            tracing::info!("⏭️  Filtered out: {}", e);
+            return;
+        }
+
+        let your_balance = match your_balance {
+            Ok(b) => b,
+            Err(e) => {
+                tracing::error!("Failed to fetch your balance: {}", e);
+                self.risk.record_data_error(&format!("Balance fetch failed: {}", e));
+                return;
+            }
+        };
+
+        let whale_balance = match whale_balance {
+            Ok(b) => b,
+            Err(e) => {
+                tracing::error!("Failed to fetch whale balance: {}", e);
+                1000000.0 // Default to large number if we can't fetch
+            }
+        };
+
+        // Calculate position size against this whale's share of the bankroll
+        let allocation = self.risk.wallet_allocation(&whale_trade.wallet, your_balance);
+        let size_usd = match self.sizer.calculate_size(&whale_trade, allocation, whale_balance).await {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::error!("Failed to calculate size: {}", e);
+                self.risk.record_data_error(&format!("Sizing failed: {}", e));
+                return;
+            }
+        };
+        let size_usd = self.sizer.scale(size_usd, size_multiplier, allocation);
+
+        if size_usd <= 0.0 {
+            tracing::warn!("⚠️  No bankroll left for {}, skipping", whale_trade.wallet.get(..10).unwrap_or(&whale_trade.wallet));
+            return;
+        }
+
+        let shares = self.sizer.shares_from_usd(size_usd, whale_trade.price);
+
+        tracing::info!("   Your size: ${:.2} ({:.2} shares)", size_usd, shares);
+
+        // Don't chase a signal that's gone stale or a price that's moved on
+        if let Err(e) = self.signal_guard.check_fresh(&whale_trade, chrono::Utc::now().timestamp_millis(), self.api.clock_skew_ms()) {
+            tracing::warn!("⏭️  Skipping: {}", e);
+            return;
+        }
+
+        let token_id = market.token_id(whale_trade.outcome);
+        let best = match self.executor.best_price(token_id, &whale_trade.side).await {
+            Ok(p) => p,
+            Err(e) => {
+                tracing::error!("Failed to fetch order book: {}", e);
+                self.risk.record_data_error(&format!("Order book fetch failed: {}", e));
+                return;
+            }
+        };
+        if let Err(e) = self.signal_guard.check_drift(&whale_trade, best) {
+            tracing::warn!("⏭️  Skipping: {}", e);
+            return;
+        }
+
+        // Risk checks. Volume and exposure count recorded fills, so copies
+        // still in flight in other markets aren't included yet.
+        if let Err(e) = self.risk.check_can_trade(&whale_trade, &market, size_usd) {
+            tracing::error!("❌ Risk check failed: {}", e);
+            return;
+        }
+
+        tracing::info!("✅ Risk checks passed");
+
+        // Execute trade
+        tracing::info!("🔄 Executing mirror trade...");
+
+        let started = Instant::now();
+        let result = self.executor.execute_trade(&whale_trade, &market, shares).await;
+        self.risk.record_latency(started.elapsed());
+
+        match result {
+            Ok(resp) => {
+                tracing::info!("✅ Trade executed successfully!");
+                tracing::info!("   Order ID: {}", resp.order_id);
+                tracing::info!("   Filled: {:.2} shares @ ${:.4}", resp.filled_shares, resp.avg_fill_price);
+                tracing::info!("   Total: ${:.2}", resp.filled_shares * resp.avg_fill_price);
+
+                self.risk.record_trade(&whale_trade, &resp);
+            }
+            Err(e) => {
+                tracing::error!("❌ Trade execution failed: {}", e);
+                self.risk.record_error(&format!("Execution failed: {}", e));
+            }
+        }
+
+        // Show circuit breaker status
+        let cb_state = self.risk.get_state();
+        tracing::info!("📈 Daily stats: {} trades, ${:.2} volume, ${:.2} PnL",
+            cb_state.total_trades_today,
+            cb_state.total_volume_today,
+            cb_state.pnl_today
+        );
+
+        let stats = self.metrics.snapshot();
+        let queue = self.queue.stats();
+        tracing::info!("📥 Pipeline: {} waiting (peak {}), {} in flight, {} processed; dropped {} stale, {} oldest",
+            queue.depth, queue.max_depth, stats.in_flight, stats.processed, queue.dropped_stale, queue.dropped_oldest);
+
+        if let Some(reason) = &cb_state.kill_reason {
+            tracing::error!("🛑 KILL SWITCH ENGAGED - {}", reason);
+        }
+
+        if cb_state.is_tripped {
+            match &cb_state.trip_reason {
+                Some(reason) => tracing::error!("⚠️  CIRCUIT BREAKER {:?} - {}", cb_state.phase, reason),
+                None => tracing::error!("⚠️  CIRCUIT BREAKER TRIPPED - Bot paused!"),
+            }
+        }
+
+        tracing::info!("---");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::{fixtures, response, MockExchange};
+    use crate::types::{OrderStatus, OverflowPolicy};
+    use std::collections::HashMap;
+
+    fn trade(market_id: &str, seq: i64) -> Trade {
+        Trade {
+            shares: 10.0,
+            timestamp: seq,
+            ..fixtures::trade(market_id)
+        }
+    }
+
+    fn queue() -> SignalQueue {
+        SignalQueue::new(10, OverflowPolicy::Block, Duration::from_secs(30), Arc::new(|_: &Trade| 0.0))
+    }
+
+    #[tokio::test]
+    async fn test_same_market_serialized_others_parallel() {
+        let rx = queue();
+        for (market_id, seq) in [("slow", 1), ("slow", 2), ("slow", 3), ("fast", 4)] {
+            rx.push(trade(market_id, seq)).await;
+        }
+        rx.close();
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let running = Arc::new(Mutex::new(HashMap::<String, u32>::new()));
+        let metrics = Arc::new(PipelineMetrics::default());
+        let (log_in, running_in) = (Arc::clone(&log), Arc::clone(&running));
+
+        WorkerPool::new(4, Arc::clone(&metrics)).run(rx, move |t| {
+            let (log, running) = (Arc::clone(&log_in), Arc::clone(&running_in));
+            async move {
+                {
+                    let mut running = running.lock().unwrap();
+                    let n = running.entry(t.market_id.clone()).or_default();
+                    assert_eq!(*n, 0, "two trades in {} at once", t.market_id);
+                    *n += 1;
+                }
+                tokio::time::sleep(Duration::from_millis(if t.market_id == "slow" { 30 } else { 1 })).await;
+                *running.lock().unwrap().get_mut(&t.market_id).unwrap() -= 1;
+                log.lock().unwrap().push(t.timestamp);
+            }
+        }).await;
+
+        // The fast market didn't wait behind the slow one, which kept its order
+        assert_eq!(*log.lock().unwrap(), vec![4, 1, 2, 3]);
+        assert_eq!(metrics.snapshot(), PipelineStats { in_flight: 0, processed: 4 });
+    }
+
+    #[tokio::test]
+    async fn test_worker_limit_and_failed_handlers() {
+        let rx = queue();
+        for i in 0..6 {
+            rx.push(trade(&format!("m{}", i % 3), i)).await;
+        }
+        rx.close();
+
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let metrics = Arc::new(PipelineMetrics::default());
+        let (active_in, peak_in) = (Arc::clone(&active), Arc::clone(&peak));
+
+        WorkerPool::new(2, Arc::clone(&metrics)).run(rx, move |t| {
+            let (active, peak) = (Arc::clone(&active_in), Arc::clone(&peak_in));
+            async move {
+                let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                active.fetch_sub(1, Ordering::SeqCst);
+                // Later trades in this market still run
+                if t.timestamp == 0 {
+                    panic!("handler blew up");
+                }
+            }
+        }).await;
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(metrics.snapshot().processed, 6);
+    }
+
+    // Shorter than a real address, as configured wallets sometimes are
+    const WHALE: &str = "0xwhale";
+    const ME: &str = "0x2222222222222222222222222222222222222222";
+
+    // A pipeline whose market data and orders all go to `exchange`
+    fn pipeline(exchange: &MockExchange, config: Config) -> TradePipeline {
+        let config = Config {
+            wallets_to_track: vec![WHALE.to_string()],
+            your_wallet: ME.to_string(),
+            market_cooldown_secs: 0,
+            retry_delay_ms: 1,
+            ..config
+        };
+        TradePipeline::new(
+            config.clone(),
+            CachedApi::new(exchange.clone(), &config),
+            Arc::new(PositionSizer::new(config.clone())),
+            Arc::new(RiskManager::new(config.clone())),
+            Arc::new(TradeExecutor::new(exchange.clone(), config)),
+        ).unwrap()
+    }
+
+    fn whale_buy() -> Trade {
+        Trade {
+            wallet: WHALE.to_string(),
+            timestamp: chrono::Utc::now().timestamp(),
+            ..fixtures::trade("market1")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_process_sizes_checks_and_executes() {
+        let exchange = MockExchange::default();
+        exchange
+            .with_market(fixtures::market("market1"))
+            .with_balance(ME, 1000.0)
+            .with_balance(WHALE, 50_000.0)
+            .with_orderbook(vec![(0.49, 500.0)], vec![(0.5, 500.0)]);
+        exchange.on_place(Ok(response("o1", OrderStatus::Filled, 50.0, 0.5)));
+        // Room for one fixed $25 stake today, not two
+        let pipeline = pipeline(&exchange, Config { max_daily_volume: 40.0, ..Default::default() });
+
+        pipeline.process(whale_buy()).await;
+
+        let placed = exchange.placed();
+        assert_eq!(placed.len(), 1);
+        assert_eq!((placed[0].token_id.as_str(), placed[0].shares), ("tok-yes", 50.0));
+        assert_eq!(pipeline.risk.cash(), Some(975.0));
+        assert_eq!(pipeline.risk.get_state().total_trades_today, 1);
+
+        // The second copy fails the daily volume check and never reaches the exchange
+        pipeline.process(whale_buy()).await;
+
+        assert_eq!(exchange.placed().len(), 1);
+        assert_eq!(pipeline.risk.cash(), Some(975.0));
+        assert_eq!(pipeline.risk.get_state().total_trades_today, 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_fills_both_come_out_of_cash() {
+        let exchange = MockExchange::default();
+        exchange
+            .with_market(fixtures::market("market1"))
+            .with_market(fixtures::market("market2"))
+            .with_balance(ME, 1000.0)
+            .with_balance(WHALE, 50_000.0)
+            .with_orderbook(vec![(0.49, 500.0)], vec![(0.5, 500.0)])
+            .with_market_delay(Duration::from_millis(50));
+        exchange.on_place(Ok(response("o1", OrderStatus::Filled, 50.0, 0.5)));
+        exchange.on_place(Ok(response("o2", OrderStatus::Filled, 50.0, 0.5)));
+        let pipeline = pipeline(&exchange, Config::default());
+
+        // The second copy reads our cash before the first fills, and is
+        // still waiting on its market when that fill lands
+        tokio::join!(
+            pipeline.process(whale_buy()),
+            async {
+                tokio::time::sleep(Duration::from_millis(25)).await;
+                pipeline.process(Trade { market_id: "market2".to_string(), ..whale_buy() }).await;
+            },
+        );
+
+        assert_eq!(exchange.placed().len(), 2);
+        assert_eq!(pipeline.risk.cash(), Some(950.0));
+    }
+}
@@ -0,0 +1,335 @@
+use crate::types::{OverflowPolicy, Trade};
+use async_channel::Receiver;
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+pub type Priority = Arc<dyn Fn(&Trade) -> f64 + Send + Sync>;
+
+#[derive(Debug, Default)]
+struct QueueMetrics {
+    max_depth: AtomicUsize,
+    dropped_oldest: AtomicU64,
+    dropped_stale: AtomicU64,
+    blocked: AtomicU64,  // Pushes that had to wait for room
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    pub depth: usize,
+    pub max_depth: usize,
+    pub dropped_oldest: u64,
+    pub dropped_stale: u64,
+    pub blocked: u64,
+}
+
+struct Queued {
+    seq: u64,
+    priority: f64,
+    trade: Trade,
+}
+
+#[derive(Default)]
+struct State {
+    lanes: HashMap<String, VecDeque<Queued>>,  // Per market, in arrival order
+    busy: HashSet<String>,  // Markets with a signal checked out
+    len: usize,
+    next_seq: u64,
+    closed: bool,
+}
+
+impl State {
+    fn take(&mut self, market_id: &str) -> Option<Queued> {
+        let lane = self.lanes.get_mut(market_id)?;
+        let queued = lane.pop_front();
+        if lane.is_empty() {
+            self.lanes.remove(market_id);
+        }
+        if queued.is_some() {
+            self.len -= 1;
+        }
+        queued
+    }
+
+    // Lane whose head ranks highest
+    fn best_head<F: Fn(&Queued, &Queued) -> CmpOrdering>(&self, cmp: F, skip_busy: bool) -> Option<String> {
+        self.lanes.iter()
+            .filter(|(market_id, _)| !(skip_busy && self.busy.contains(*market_id)))
+            .filter_map(|(market_id, lane)| lane.front().map(|head| (market_id, head)))
+            .max_by(|(_, a), (_, b)| cmp(a, b))
+            .map(|(market_id, _)| market_id.clone())
+    }
+
+    fn remove_stale(&mut self, cutoff: i64) -> usize {
+        let before = self.len;
+        for lane in self.lanes.values_mut() {
+            lane.retain(|q| q.trade.timestamp >= cutoff);
+        }
+        self.lanes.retain(|_, lane| !lane.is_empty());
+        self.len = self.lanes.values().map(VecDeque::len).sum();
+        before - self.len
+    }
+}
+
+struct Inner {
+    state: Mutex<State>,
+    items: Notify,
+    space: Notify,
+    capacity: usize,
+    policy: OverflowPolicy,
+    max_age: Duration,
+    priority: Priority,
+    metrics: QueueMetrics,
+}
+
+// Bounded hand-off from the watchers to the pipeline. Unless the policy is
+// Block, pushing never waits, so a stalled pipeline costs signals rather
+// than socket reads. The highest-priority signal goes first, but a market's
+// signals are checked out one at a time, in the order they arrived: the next
+// one isn't handed out until the previous is released.
+#[derive(Clone)]
+pub struct SignalQueue {
+    inner: Arc<Inner>,
+}
+
+impl SignalQueue {
+    pub fn new(capacity: usize, policy: OverflowPolicy, max_age: Duration, priority: Priority) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                state: Mutex::new(State::default()),
+                items: Notify::new(),
+                space: Notify::new(),
+                capacity: capacity.max(1),
+                policy,
+                max_age,
+                priority,
+                metrics: QueueMetrics::default(),
+            }),
+        }
+    }
+
+    pub async fn push(&self, trade: Trade) {
+        let inner = &self.inner;
+        let mut waited = false;
+        loop {
+            let space = inner.space.notified();
+            tokio::pin!(space);
+            space.as_mut().enable();
+
+            {
+                let mut state = inner.state.lock().unwrap();
+                if state.closed {
+                    return;
+                }
+                if state.len >= inner.capacity {
+                    self.make_room(&mut state);
+                }
+                if state.len < inner.capacity {
+                    let seq = state.next_seq;
+                    state.next_seq += 1;
+                    let priority = (inner.priority)(&trade);
+                    state.lanes.entry(trade.market_id.clone()).or_default()
+                        .push_back(Queued { seq, priority, trade });
+                    state.len += 1;
+                    inner.metrics.max_depth.fetch_max(state.len, Ordering::Relaxed);
+                    drop(state);
+                    inner.items.notify_one();
+                    return;
+                }
+            }
+
+            // Only Block gets here
+            if !waited {
+                waited = true;
+                inner.metrics.blocked.fetch_add(1, Ordering::Relaxed);
+            }
+            space.await;
+        }
+    }
+
+    fn make_room(&self, state: &mut State) {
+        let inner = &self.inner;
+        if inner.policy == OverflowPolicy::Block {
+            return;
+        }
+
+        if inner.policy == OverflowPolicy::DropStale {
+            let dropped = state.remove_stale(self.stale_cutoff());
+            if dropped > 0 {
+                inner.metrics.dropped_stale.fetch_add(dropped as u64, Ordering::Relaxed);
+                tracing::warn!("Signal queue full, dropped {} stale signals", dropped);
+                return;
+            }
+        }
+
+        // Nothing stale to shed: the oldest signal is the least likely to still be copyable
+        let oldest = state.best_head(|a, b| b.seq.cmp(&a.seq), false);
+        if let Some(dropped) = oldest.and_then(|market_id| state.take(&market_id)) {
+            inner.metrics.dropped_oldest.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!("Signal queue full, dropped oldest signal from {} on {}",
+                dropped.trade.wallet, dropped.trade.market_id);
+        }
+    }
+
+    fn stale_cutoff(&self) -> i64 {
+        chrono::Utc::now().timestamp() - self.inner.max_age.as_secs() as i64
+    }
+
+    // The next signal from a market that isn't checked out, or None once
+    // closed and drained. The caller must `release` the market when done.
+    pub async fn recv(&self) -> Option<Trade> {
+        let inner = &self.inner;
+        loop {
+            let items = inner.items.notified();
+            tokio::pin!(items);
+            items.as_mut().enable();
+
+            {
+                let mut state = inner.state.lock().unwrap();
+                if inner.policy == OverflowPolicy::DropStale {
+                    // Would be rejected downstream anyway
+                    let dropped = state.remove_stale(self.stale_cutoff());
+                    if dropped > 0 {
+                        inner.metrics.dropped_stale.fetch_add(dropped as u64, Ordering::Relaxed);
+                    }
+                }
+                let next = state.best_head(|a, b| a.priority.total_cmp(&b.priority).then(b.seq.cmp(&a.seq)), true)
+                    .and_then(|market_id| state.take(&market_id));
+                if let Some(queued) = next {
+                    state.busy.insert(queued.trade.market_id.clone());
+                    drop(state);
+                    inner.space.notify_one();
+                    return Some(queued.trade);
+                }
+                if state.closed && state.len == 0 {
+                    return None;
+                }
+            }
+
+            items.await;
+        }
+    }
+
+    pub fn release(&self, market_id: &str) {
+        self.inner.state.lock().unwrap().busy.remove(market_id);
+        self.inner.items.notify_one();
+    }
+
+    // Stops accepting signals; what's queued can still be received
+    pub fn close(&self) {
+        self.inner.state.lock().unwrap().closed = true;
+        self.inner.items.notify_waiters();
+        self.inner.space.notify_waiters();
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.state.lock().unwrap().len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        let metrics = &self.inner.metrics;
+        QueueStats {
+            depth: self.len(),
+            max_depth: metrics.max_depth.load(Ordering::Relaxed),
+            dropped_oldest: metrics.dropped_oldest.load(Ordering::Relaxed),
+            dropped_stale: metrics.dropped_stale.load(Ordering::Relaxed),
+            blocked: metrics.blocked.load(Ordering::Relaxed),
+        }
+    }
+
+    // Moves everything from a channel into the queue, closing it when the channel ends
+    pub fn forward_from(&self, input: Receiver<Trade>) {
+        let queue = self.clone();
+        tokio::spawn(async move {
+            while let Ok(trade) = input.recv().await {
+                queue.push(trade).await;
+            }
+            queue.close();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::fixtures;
+
+    fn trade(market_id: &str, shares: f64, timestamp: i64) -> Trade {
+        Trade {
+            shares,
+            timestamp,
+            ..fixtures::trade(market_id)
+        }
+    }
+
+    fn by_size() -> Priority {
+        Arc::new(|t: &Trade| t.shares * t.price)
+    }
+
+    fn now() -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+
+    #[tokio::test]
+    async fn test_large_signals_first_within_market_order() {
+        let queue = SignalQueue::new(10, OverflowPolicy::Block, Duration::from_secs(30), by_size());
+        queue.push(trade("a", 10.0, now())).await;
+        queue.push(trade("a", 5000.0, now())).await;
+        queue.push(trade("b", 1000.0, now())).await;
+        queue.push(trade("c", 10.0, now())).await;
+        queue.close();
+
+        assert_eq!(queue.recv().await.unwrap().market_id, "b");
+        // The big trade in `a` has to wait behind the small one before it
+        let first_a = queue.recv().await.unwrap();
+        assert_eq!(first_a.shares, 10.0);
+        // ...and until that one is released
+        assert_eq!(queue.recv().await.unwrap().market_id, "c");
+        queue.release("a");
+        assert_eq!(queue.recv().await.unwrap().shares, 5000.0);
+        assert!(queue.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_drop_policies_never_block() {
+        let queue = SignalQueue::new(2, OverflowPolicy::DropOldest, Duration::from_secs(30), by_size());
+        for (i, market_id) in ["a", "b", "c"].into_iter().enumerate() {
+            queue.push(trade(market_id, 10.0 + i as f64, now())).await;
+        }
+        assert_eq!(queue.stats().dropped_oldest, 1);
+        assert_eq!(queue.recv().await.unwrap().market_id, "c");
+        assert_eq!(queue.recv().await.unwrap().market_id, "b");
+        assert!(queue.is_empty());
+
+        let queue = SignalQueue::new(2, OverflowPolicy::DropStale, Duration::from_secs(30), by_size());
+        queue.push(trade("a", 10.0, now())).await;
+        queue.push(trade("b", 10.0, now() - 60)).await;
+        queue.push(trade("c", 10.0, now())).await;
+        let stats = queue.stats();
+        assert_eq!((stats.dropped_stale, stats.dropped_oldest, stats.depth), (1, 0, 2));
+    }
+
+    #[tokio::test]
+    async fn test_block_waits_for_room() {
+        let queue = SignalQueue::new(1, OverflowPolicy::Block, Duration::from_secs(30), by_size());
+        queue.push(trade("a", 10.0, now())).await;
+
+        let pusher = queue.clone();
+        let blocked = tokio::spawn(async move { pusher.push(trade("b", 10.0, now())).await });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!blocked.is_finished());
+        assert_eq!(queue.stats().blocked, 1);
+
+        assert_eq!(queue.recv().await.unwrap().market_id, "a");
+        queue.release("a");
+        blocked.await.unwrap();
+        assert_eq!(queue.recv().await.unwrap().market_id, "b");
+    }
+}
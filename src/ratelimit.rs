@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    Markets,
+    Events,
+    Trades,
+    Orderbook,
+    PlaceOrder,
+    Order,  // Status and single cancels
+    CancelAll,
+    Balance,
+}
+
+impl Endpoint {
+    pub const ALL: [Endpoint; 8] = [
+        Endpoint::Markets,
+        Endpoint::Events,
+        Endpoint::Trades,
+        Endpoint::Orderbook,
+        Endpoint::PlaceOrder,
+        Endpoint::Order,
+        Endpoint::CancelAll,
+        Endpoint::Balance,
+    ];
+
+    // Requests per 10 seconds, as published in the exchange's API docs
+    pub fn published_limit(self) -> u32 {
+        match self {
+            Endpoint::Markets => 125,
+            Endpoint::Events => 100,
+            Endpoint::Trades => 75,
+            Endpoint::Orderbook => 200,
+            Endpoint::PlaceOrder => 2400,
+            Endpoint::Order => 2400,
+            Endpoint::CancelAll => 200,
+            Endpoint::Balance => 125,
+        }
+    }
+}
+
+const LIMIT_WINDOW: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    per_sec: f64,
+    tokens: f64,  // Goes negative while requests are queued for a token
+    refilled: Instant,
+    paused_until: Option<Instant>,
+}
+
+impl TokenBucket {
+    fn new(limit: f64, now: Instant) -> Self {
+        let capacity = limit.max(1.0);
+        Self {
+            capacity,
+            per_sec: capacity / LIMIT_WINDOW.as_secs_f64(),
+            tokens: capacity,
+            refilled: now,
+            paused_until: None,
+        }
+    }
+
+    // Takes a token and says how long to wait before using it. Reserving
+    // rather than retrying keeps waiters in arrival order.
+    fn reserve(&mut self, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_sec).min(self.capacity);
+        self.refilled = now;
+        self.tokens -= 1.0;
+
+        let refill_wait = if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.per_sec)
+        };
+        let pause_wait = self.paused_until
+            .map(|until| until.saturating_duration_since(now))
+            .unwrap_or_default();
+        refill_wait.max(pause_wait)
+    }
+
+    fn pause(&mut self, until: Instant) {
+        self.paused_until = Some(self.paused_until.map_or(until, |p| p.max(until)));
+    }
+}
+
+// Client-side limits per endpoint, kept a little under the exchange's so a
+// burst of whale signals queues here instead of earning 429s. A 429 that
+// still gets through pauses its endpoint for the Retry-After period.
+#[derive(Debug)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<Endpoint, TokenBucket>>,
+}
+
+impl RateLimiter {
+    // `headroom` is the fraction of each published limit to use
+    pub fn new(headroom: f64) -> Self {
+        let now = Instant::now();
+        let buckets = Endpoint::ALL.iter()
+            .map(|&e| (e, TokenBucket::new(e.published_limit() as f64 * headroom, now)))
+            .collect();
+        Self { buckets: Mutex::new(buckets) }
+    }
+
+    pub async fn acquire(&self, endpoint: Endpoint) {
+        let wait = self.reserve(endpoint, Instant::now());
+        if !wait.is_zero() {
+            tracing::debug!("Rate limited locally on {:?}, waiting {:?}", endpoint, wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn reserve(&self, endpoint: Endpoint, now: Instant) -> Duration {
+        self.buckets.lock().unwrap()
+            .get_mut(&endpoint)
+            .map(|bucket| bucket.reserve(now))
+            .unwrap_or_default()
+    }
+
+    pub fn pause(&self, endpoint: Endpoint, retry_after: Duration) {
+        tracing::warn!("Exchange rate limited {:?}, pausing for {:?}", endpoint, retry_after);
+        if let Some(bucket) = self.buckets.lock().unwrap().get_mut(&endpoint) {
+            bucket.pause(Instant::now() + retry_after);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_bursts_then_paces() {
+        let t0 = Instant::now();
+        let mut bucket = TokenBucket::new(20.0, t0);  // 2 per second
+
+        for _ in 0..20 {
+            assert_eq!(bucket.reserve(t0), Duration::ZERO);
+        }
+        // Queued callers wait their turn, half a second apart
+        assert_eq!(bucket.reserve(t0), Duration::from_millis(500));
+        assert_eq!(bucket.reserve(t0), Duration::from_millis(1000));
+
+        // Two seconds later those two reservations are paid off, plus two more tokens
+        let t2 = t0 + Duration::from_secs(2);
+        assert_eq!(bucket.reserve(t2), Duration::ZERO);
+        assert_eq!(bucket.reserve(t2), Duration::ZERO);
+        assert_eq!(bucket.reserve(t2), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_retry_after_pauses_one_endpoint() {
+        let limiter = RateLimiter::new(1.0);
+        limiter.pause(Endpoint::Orderbook, Duration::from_secs(5));
+
+        let now = Instant::now();
+        assert!(limiter.reserve(Endpoint::Orderbook, now) > Duration::from_secs(4));
+        assert_eq!(limiter.reserve(Endpoint::PlaceOrder, now), Duration::ZERO);
+        assert_eq!(limiter.reserve(Endpoint::Orderbook, now + Duration::from_secs(5)), Duration::ZERO);
+    }
+}
@@ -1,54 +1,120 @@
-use crate::types::{Config, CircuitBreakerState, Trade, Market};
+use crate::breaker::{BreakerPhase, CircuitBreaker, TripReason};
+use crate::exposure::ExposureBook;
+use crate::killswitch::KillSwitch;
+use crate::limits::RateLimits;
+use crate::types::{Config, CircuitBreakerState, Event, Trade, TradeSide, Market, OrderResponse};
 use anyhow::{Result, bail};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+#[derive(Debug, Default)]
+struct DailyStats {
+    total_trades: u32,
+    total_volume: f64,
+    volume_by_wallet: HashMap<String, f64>,  // Lowercase wallet
+    realized_pnl: f64,
+    unrealized_at_open: f64,  // Unrealized PnL when the day started
+}
 
 pub struct RiskManager {
     config: Config,
-    state: Arc<Mutex<CircuitBreakerState>>,
-    event_exposure: Arc<Mutex<HashMap<String, f64>>>,
+    daily: Arc<Mutex<DailyStats>>,
+    breaker: Arc<Mutex<CircuitBreaker>>,
+    exposure: Arc<Mutex<ExposureBook>>,
+    tripped_tx: watch::Sender<bool>,
+    kill_switch: Arc<Mutex<KillSwitch>>,
+    limits: Arc<Mutex<RateLimits>>,
+    cash: Arc<Mutex<Option<f64>>>,  // Last known balance, adjusted by our own fills
+    killed_tx: watch::Sender<bool>,
+    disabled_wallets: Arc<Mutex<HashMap<String, String>>>,  // Lowercase wallet -> why
 }
 
 impl RiskManager {
     pub fn new(config: Config) -> Self {
         Self {
+            breaker: Arc::new(Mutex::new(CircuitBreaker::new(config.clone()))),
+            kill_switch: Arc::new(Mutex::new(KillSwitch::new(config.clone()))),
+            limits: Arc::new(Mutex::new(RateLimits::new(config.clone()))),
             config,
-            state: Arc::new(Mutex::new(CircuitBreakerState {
-                consecutive_errors: 0,
-                total_trades_today: 0,
-                total_volume_today: 0.0,
-                is_tripped: false,
-                trip_reason: None,
-            })),
-            event_exposure: Arc::new(Mutex::new(HashMap::new())),
+            daily: Arc::new(Mutex::new(DailyStats::default())),
+            exposure: Arc::new(Mutex::new(ExposureBook::new())),
+            tripped_tx: watch::channel(false).0,
+            cash: Arc::new(Mutex::new(None)),
+            killed_tx: watch::channel(false).0,
+            disabled_wallets: Arc::new(Mutex::new(HashMap::new())),
         }
     }
     
+    // Lets other components (e.g. the order manager) react the moment the breaker trips
+    pub fn breaker_signal(&self) -> watch::Receiver<bool> {
+        self.tripped_tx.subscribe()
+    }
+    
+    // Fires when the loss kill switch engages, e.g. to flatten positions
+    pub fn kill_signal(&self) -> watch::Receiver<bool> {
+        self.killed_tx.subscribe()
+    }
+    
     pub fn check_can_trade(&self, trade: &Trade, market: &Market, size_usd: f64) -> Result<()> {
-        // Check if circuit breaker is tripped
-        {
-            let state = self.state.lock().unwrap();
-            if state.is_tripped {
-                bail!("Circuit breaker tripped: {}", state.trip_reason.as_ref().unwrap_or(&"Unknown".to_string()));
+        // Kill switch blocks new entries; exits are still allowed
+        if trade.side == TradeSide::BUY {
+            if let Some(reason) = self.kill_switch.lock().unwrap().reason() {
+                bail!("Kill switch engaged: {}", reason);
+            }
+            
+            // Wallets benched for poor performance can still be followed out
+            if let Some(reason) = self.disabled_wallets.lock().unwrap().get(&trade.wallet.to_lowercase()) {
+                bail!("Wallet {} is disabled: {}", trade.wallet, reason);
             }
         }
         
         // Check daily volume limit
         {
-            let state = self.state.lock().unwrap();
-            if state.total_volume_today + size_usd > self.config.max_daily_volume {
+            let daily = self.daily.lock().unwrap();
+            if daily.total_volume + size_usd > self.config.max_daily_volume {
                 bail!("Daily volume limit exceeded: ${:.2} + ${:.2} > ${:.2}",
-                    state.total_volume_today, size_usd, self.config.max_daily_volume);
+                    daily.total_volume, size_usd, self.config.max_daily_volume);
+            }
+            
+            let budget = self.config.wallet_budget(&trade.wallet);
+            let wallet_volume = daily.volume_by_wallet.get(&trade.wallet.to_lowercase()).copied().unwrap_or(0.0);
+            if wallet_volume + size_usd > budget.max_daily_volume {
+                bail!("Daily volume limit for {} exceeded: ${:.2} + ${:.2} > ${:.2}",
+                    trade.wallet, wallet_volume, size_usd, budget.max_daily_volume);
             }
         }
         
-        // Check event exposure limit
-        {
-            let exposure = self.event_exposure.lock().unwrap();
-            let current_exposure = exposure.get(&trade.event_id).copied().unwrap_or(0.0);
-            if current_exposure + size_usd > self.config.max_exposure_per_event {
-                bail!("Event exposure limit exceeded: ${:.2} + ${:.2} > ${:.2}",
-                    current_exposure, size_usd, self.config.max_exposure_per_event);
+        // Check event exposure limit. Sells only ever reduce capital at risk.
+        if trade.side == TradeSide::BUY {
+            let exposure = self.exposure.lock().unwrap();
+            let current_exposure = exposure.event_at_risk(&trade.event_id);
+            let projected = exposure.projected_event_at_risk(
+                &trade.event_id,
+                &trade.market_id,
+                trade.outcome,
+                size_usd / trade.price.max(f64::EPSILON),
+                size_usd,
+            );
+            if projected > self.config.max_exposure_per_event {
+                bail!("Event exposure limit exceeded: ${:.2} -> ${:.2} > ${:.2}",
+                    current_exposure, projected, self.config.max_exposure_per_event);
+            }
+            
+            self.check_portfolio(&exposure, trade, size_usd)?;
+            
+            let budget = self.config.wallet_budget(&trade.wallet);
+            let projected = exposure.projected_wallet_at_risk(
+                &trade.wallet,
+                &trade.market_id,
+                trade.outcome,
+                size_usd / trade.price.max(f64::EPSILON),
+                size_usd,
+            );
+            if projected > budget.max_exposure {
+                bail!("Exposure limit for {} exceeded: ${:.2} -> ${:.2} > ${:.2}",
+                    trade.wallet, exposure.wallet_at_risk(&trade.wallet), projected, budget.max_exposure);
             }
         }
         
@@ -65,61 +131,325 @@ impl RiskManager {
                 market.liquidity, self.config.cb_min_depth_usd);
         }
         
+        // Check rolling rate and volume limits
+        let now = Instant::now();
+        self.limits.lock().unwrap().check(now, trade, size_usd)?;
+        
+        // Check circuit breaker last: in half-open this claims a probe slot,
+        // which only the record_trade/record_error that follows will release
+        {
+            let mut breaker = self.breaker.lock().unwrap();
+            let allowed = breaker.allow_trade(now);
+            self.sync_signal(&breaker);
+            if let Err(reason) = allowed {
+                bail!("Circuit breaker tripped: {}", reason);
+            }
+        }
+        
+        self.limits.lock().unwrap().record_copy(now, trade);
+        
         tracing::info!("Risk checks passed for trade on {}", trade.market_id);
         Ok(())
     }
     
-    pub fn record_trade(&self, trade: &Trade, size_usd: f64) {
-        let mut state = self.state.lock().unwrap();
-        state.total_trades_today += 1;
-        state.total_volume_today += size_usd;
-        state.consecutive_errors = 0; // Reset on successful trade
+    // Concentration limits relative to equity, for a BUY of `size_usd`
+    fn check_portfolio(&self, exposure: &ExposureBook, trade: &Trade, size_usd: f64) -> Result<()> {
+        if exposure.position(&trade.market_id).is_none()
+            && exposure.open_positions() >= self.config.max_open_positions
+        {
+            bail!("Open position limit reached: {} markets held", exposure.open_positions());
+        }
+        
+        // Fractions need equity, which we only know once a balance has come in
+        let Some(cash) = *self.cash.lock().unwrap() else {
+            tracing::debug!("No balance recorded yet, skipping concentration checks");
+            return Ok(());
+        };
+        let gross = exposure.market_value();
+        let equity = cash + gross;
+        if equity <= 0.0 {
+            bail!("No equity to trade against");
+        }
+        let shares = size_usd / trade.price.max(f64::EPSILON);
+        
+        let market = exposure.projected_market_at_risk(&trade.market_id, trade.outcome, shares, size_usd);
+        if market / equity > self.config.max_market_concentration {
+            bail!("Market concentration limit exceeded: ${:.2} is {:.1}% of ${:.2} equity (max {:.1}%)",
+                market, market / equity * 100.0, equity, self.config.max_market_concentration * 100.0);
+        }
+        
+        let event = exposure.projected_event_at_risk(&trade.event_id, &trade.market_id, trade.outcome, shares, size_usd);
+        if event / equity > self.config.max_event_concentration {
+            bail!("Event concentration limit exceeded: ${:.2} is {:.1}% of ${:.2} equity (max {:.1}%)",
+                event, event / equity * 100.0, equity, self.config.max_event_concentration * 100.0);
+        }
+        
+        if (gross + size_usd) / equity > self.config.max_gross_exposure_pct {
+            bail!("Gross exposure limit exceeded: ${:.2} + ${:.2} over ${:.2} equity (max {:.1}%)",
+                gross, size_usd, equity, self.config.max_gross_exposure_pct * 100.0);
+        }
+        
+        Ok(())
+    }
+    
+    // A mirror order went through: settles the breaker and books any fill
+    pub fn record_trade(&self, trade: &Trade, resp: &OrderResponse) {
+        {
+            let mut breaker = self.breaker.lock().unwrap();
+            breaker.on_success(Instant::now());
+            
+            if resp.filled_shares > 0.0 && trade.price > 0.0 {
+                // Adverse move from the limit we sent (the whale's price)
+                let slippage = match trade.side {
+                    TradeSide::BUY => resp.avg_fill_price - trade.price,
+                    TradeSide::SELL => trade.price - resp.avg_fill_price,
+                };
+                breaker.on_slippage(Instant::now(), &trade.market_id, slippage / trade.price * 10_000.0);
+            }
+            self.sync_signal(&breaker);
+        }
+        
+        self.record_fill(trade, resp);
+    }
+    
+    // Books a fill without counting it as an execution outcome, e.g. a resting order filling later
+    pub fn record_fill(&self, trade: &Trade, resp: &OrderResponse) {
+        if resp.filled_shares <= 0.0 {
+            tracing::info!("Order {} has no fills yet, nothing to record", resp.order_id);
+            return;
+        }
+        
+        // Account for what actually filled, not what we asked for
+        let notional = resp.filled_shares * resp.avg_fill_price;
+        {
+            let mut daily = self.daily.lock().unwrap();
+            daily.total_trades += 1;
+            daily.total_volume += notional + resp.fees;
+            *daily.volume_by_wallet.entry(trade.wallet.to_lowercase()).or_default() += notional + resp.fees;
+            
+            let mut exposure = self.exposure.lock().unwrap();
+            let realized = exposure.apply_fill(trade, resp.filled_shares, resp.avg_fill_price, resp.fees);
+            daily.realized_pnl += realized;
+            
+            tracing::info!(
+                "Trade recorded: #{} today, ${:.2} volume, ${:.2} event exposure, ${:.2} realized",
+                daily.total_trades,
+                daily.total_volume,
+                exposure.event_at_risk(&trade.event_id),
+                realized
+            );
+            
+            let mut breaker = self.breaker.lock().unwrap();
+            breaker.on_daily_pnl(Instant::now(), daily.realized_pnl);
+            self.sync_signal(&breaker);
+        }
+        
+        self.limits.lock().unwrap().record_volume(Instant::now(), notional + resp.fees);
+        
+        if let Some(cash) = self.cash.lock().unwrap().as_mut() {
+            match trade.side {
+                TradeSide::BUY => *cash -= notional + resp.fees,
+                TradeSide::SELL => *cash += notional - resp.fees,
+            }
+        }
         
-        let mut exposure = self.event_exposure.lock().unwrap();
-        *exposure.entry(trade.event_id.clone()).or_insert(0.0) += size_usd;
+        self.evaluate_kill_switch();
+    }
+    
+    // Refreshes the mark used to value our position in this market
+    pub fn mark_market(&self, market: &Market) {
+        self.exposure.lock().unwrap().mark(market);
+        self.evaluate_kill_switch();
+    }
+    
+    // Realized plus marked-to-market unrealized PnL since the daily reset
+    pub fn pnl_today(&self) -> f64 {
+        let daily = self.daily.lock().unwrap();
+        let unrealized = self.exposure.lock().unwrap().unrealized_pnl();
+        daily.realized_pnl + unrealized - daily.unrealized_at_open
+    }
+    
+    fn evaluate_kill_switch(&self) {
+        let pnl_today = self.pnl_today();
+        let market_value = self.exposure.lock().unwrap().market_value();
+        let equity = self.cash.lock().unwrap().map(|cash| cash + market_value);
         
-        tracing::info!(
-            "Trade recorded: #{} today, ${:.2} volume, ${:.2} event exposure",
-            state.total_trades_today,
-            state.total_volume_today,
-            exposure.get(&trade.event_id).unwrap_or(&0.0)
-        );
+        let mut kill_switch = self.kill_switch.lock().unwrap();
+        kill_switch.update(pnl_today, equity);
+        self.sync_kill_signal(&kill_switch);
     }
     
+    fn sync_kill_signal(&self, kill_switch: &KillSwitch) {
+        let engaged = kill_switch.is_engaged();
+        self.killed_tx.send_if_modified(|killed| {
+            let changed = *killed != engaged;
+            *killed = engaged;
+            changed
+        });
+    }
+    
+    pub fn reset_kill_switch(&self) {
+        let mut kill_switch = self.kill_switch.lock().unwrap();
+        kill_switch.reset();
+        self.sync_kill_signal(&kill_switch);
+        tracing::info!("Kill switch reset");
+    }
+    
+    // A mirror order failed; in half-open this fails the probe
     pub fn record_error(&self, error: &str) {
-        let mut state = self.state.lock().unwrap();
-        state.consecutive_errors += 1;
+        let mut breaker = self.breaker.lock().unwrap();
+        breaker.on_error(Instant::now(), error);
         
-        tracing::warn!("Error recorded: {} (consecutive: {})", error, state.consecutive_errors);
+        tracing::warn!("Error recorded: {} (consecutive: {})", error, breaker.consecutive_errors());
+        self.sync_signal(&breaker);
+    }
+    
+    // Something failed before an order was sent
+    pub fn record_data_error(&self, error: &str) {
+        let mut breaker = self.breaker.lock().unwrap();
+        breaker.on_data_error(Instant::now());
         
-        if state.consecutive_errors >= self.config.cb_consecutive_trigger {
-            state.is_tripped = true;
-            state.trip_reason = Some(format!("Too many consecutive errors: {}", state.consecutive_errors));
-            tracing::error!("CIRCUIT BREAKER TRIPPED: {}", state.trip_reason.as_ref().unwrap());
+        tracing::warn!("Error recorded: {} (consecutive: {})", error, breaker.consecutive_errors());
+        self.sync_signal(&breaker);
+    }
+    
+    pub fn record_latency(&self, latency: Duration) {
+        let mut breaker = self.breaker.lock().unwrap();
+        breaker.on_latency(Instant::now(), latency);
+        self.sync_signal(&breaker);
+    }
+    
+    // Last known balance, moved by our own fills since it was fetched
+    pub fn cash(&self) -> Option<f64> {
+        *self.cash.lock().unwrap()
+    }
+    
+    // Equity is cash plus the cost basis of open positions for the breaker,
+    // and cash plus marked value for the kill switch
+    pub fn record_balance(&self, cash: f64) {
+        *self.cash.lock().unwrap() = Some(cash);
+        self.on_balance(cash);
+    }
+    
+    // Starts cash tracking from a fetched balance, unless another caller got
+    // there first and fills have moved it on since. Returns the tracked cash.
+    pub fn seed_balance(&self, cash: f64) -> f64 {
+        {
+            let mut tracked = self.cash.lock().unwrap();
+            if let Some(tracked) = *tracked {
+                return tracked;
+            }
+            *tracked = Some(cash);
+        }
+        self.on_balance(cash);
+        cash
+    }
+    
+    fn on_balance(&self, cash: f64) {
+        {
+            let equity = cash + self.exposure.lock().unwrap().total_cost();
+            let mut breaker = self.breaker.lock().unwrap();
+            breaker.on_equity(Instant::now(), equity);
+            self.sync_signal(&breaker);
         }
+        self.evaluate_kill_switch();
+    }
+    
+    pub fn trip_circuit_breaker(&self, reason: TripReason) {
+        let mut breaker = self.breaker.lock().unwrap();
+        breaker.trip(Instant::now(), reason);
+        self.sync_signal(&breaker);
     }
     
     pub fn reset_circuit_breaker(&self) {
-        let mut state = self.state.lock().unwrap();
-        state.is_tripped = false;
-        state.consecutive_errors = 0;
-        state.trip_reason = None;
+        let mut breaker = self.breaker.lock().unwrap();
+        breaker.reset();
+        self.sync_signal(&breaker);
         tracing::info!("Circuit breaker reset");
     }
     
+    fn sync_signal(&self, breaker: &CircuitBreaker) {
+        let open = breaker.phase() == BreakerPhase::Open;
+        self.tripped_tx.send_if_modified(|tripped| {
+            let changed = *tripped != open;
+            *tripped = open;
+            changed
+        });
+    }
+    
     pub fn reset_daily_stats(&self) {
-        let mut state = self.state.lock().unwrap();
-        state.total_trades_today = 0;
-        state.total_volume_today = 0.0;
+        {
+            let mut daily = self.daily.lock().unwrap();
+            *daily = DailyStats {
+                unrealized_at_open: self.exposure.lock().unwrap().unrealized_pnl(),
+                ..Default::default()
+            };
+        }
         
-        let mut exposure = self.event_exposure.lock().unwrap();
-        exposure.clear();
+        let mut kill_switch = self.kill_switch.lock().unwrap();
+        kill_switch.roll_day();
+        self.sync_kill_signal(&kill_switch);
         
+        // Positions carry over midnight, so exposure is deliberately left alone
         tracing::info!("Daily stats reset");
     }
     
     pub fn get_state(&self) -> CircuitBreakerState {
-        self.state.lock().unwrap().clone()
+        let pnl_today = self.pnl_today();
+        let kill_reason = self.kill_switch.lock().unwrap().reason().cloned();
+        let daily = self.daily.lock().unwrap();
+        let breaker = self.breaker.lock().unwrap();
+        CircuitBreakerState {
+            consecutive_errors: breaker.consecutive_errors(),
+            total_trades_today: daily.total_trades,
+            total_volume_today: daily.total_volume,
+            realized_pnl_today: daily.realized_pnl,
+            phase: breaker.phase(),
+            is_tripped: breaker.is_tripped(),
+            trip_reason: breaker.reason().cloned(),
+            pnl_today,
+            kill_reason,
+        }
+    }
+    
+    // Neg-risk events need their full market set before exposure can be scored
+    pub fn register_event(&self, event: &Event) {
+        self.exposure.lock().unwrap().register_event(event);
+        tracing::info!("Registered {}event {} with {} markets",
+            if event.neg_risk { "neg-risk " } else { "" }, event.id, event.markets.len());
+    }
+    
+    pub fn knows_event(&self, event_id: &str) -> bool {
+        self.exposure.lock().unwrap().knows_event(event_id)
+    }
+    
+    pub fn exposure(&self) -> ExposureBook {
+        self.exposure.lock().unwrap().clone()
+    }
+    
+    // What a copy of `wallet` may size against: its bankroll less what its
+    // copies already have at risk, and never more than we actually hold
+    pub fn wallet_allocation(&self, wallet: &str, balance: f64) -> f64 {
+        let at_risk = self.exposure.lock().unwrap().wallet_at_risk(wallet);
+        let bankroll = self.config.wallet_budget(wallet).bankroll;
+        (bankroll - at_risk).max(0.0).min(balance)
+    }
+    
+    pub fn disable_wallet(&self, wallet: &str, reason: &str) {
+        let previous = self.disabled_wallets.lock().unwrap().insert(wallet.to_lowercase(), reason.to_string());
+        if previous.is_none() {
+            tracing::warn!("Disabled copying of {}: {}", wallet, reason);
+        }
+    }
+    
+    pub fn enable_wallet(&self, wallet: &str) {
+        if self.disabled_wallets.lock().unwrap().remove(&wallet.to_lowercase()).is_some() {
+            tracing::info!("Re-enabled copying of {}", wallet);
+        }
+    }
+    
+    pub fn is_wallet_disabled(&self, wallet: &str) -> bool {
+        self.disabled_wallets.lock().unwrap().contains_key(&wallet.to_lowercase())
     }
     
     pub fn is_whale_verified(&self, wallet: &str) -> bool {
@@ -131,6 +461,9 @@ impl RiskManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::api::mock::fixtures;
+    use crate::killswitch::KillReason;
+    use crate::types::{OrderStatus, WalletBudget};
     
     #[test]
     fn test_circuit_breaker() {
@@ -162,4 +495,257 @@ mod tests {
         risk.reset_circuit_breaker();
         assert!(!risk.get_state().is_tripped);
     }
+    
+    #[test]
+    fn test_record_trade_uses_actual_fills() {
+        let risk = RiskManager::new(Config::default());
+        let mut trade = fixtures::trade("market1");
+        
+        // Asked for 100 shares, only 40 filled at a worse price
+        let fill = OrderResponse {
+            order_id: "o1".to_string(),
+            status: OrderStatus::PartiallyFilled,
+            filled_shares: 40.0,
+            avg_fill_price: 0.55,
+            fees: 0.5,
+        };
+        risk.record_trade(&trade, &fill);
+        
+        let state = risk.get_state();
+        assert_eq!(state.total_trades_today, 1);
+        assert!((state.total_volume_today - 22.5).abs() < 1e-9);
+        assert!((risk.exposure().event_at_risk("event1") - 22.5).abs() < 1e-9);
+        
+        // Selling half back releases half the cost basis instead of adding to it
+        trade.side = TradeSide::SELL;
+        let sell = OrderResponse { filled_shares: 20.0, avg_fill_price: 0.6, fees: 0.0, ..fill };
+        risk.record_trade(&trade, &sell);
+        
+        assert!((risk.exposure().event_at_risk("event1") - 11.25).abs() < 1e-9);
+        assert!((risk.get_state().total_volume_today - 34.5).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn test_slippage_trips_breaker_with_reason() {
+        let config = Config { cb_max_slippage_bps: 300.0, ..Default::default() };
+        let risk = RiskManager::new(config);
+        let trade = fixtures::trade("market1");
+        let mut signal = risk.breaker_signal();
+        
+        // Whale paid 0.50, we filled at 0.53: 600bps against us
+        risk.record_trade(&trade, &OrderResponse {
+            order_id: "o1".to_string(),
+            status: OrderStatus::Filled,
+            filled_shares: 10.0,
+            avg_fill_price: 0.53,
+            fees: 0.0,
+        });
+        
+        let state = risk.get_state();
+        assert_eq!(state.phase, BreakerPhase::Open);
+        assert!(matches!(state.trip_reason, Some(TripReason::Slippage { .. })));
+        assert!(signal.has_changed().unwrap());
+        assert!(*signal.borrow_and_update());
+    }
+    
+    #[test]
+    fn test_unrealized_loss_engages_kill_switch() {
+        let config = Config {
+            max_daily_loss: 50.0,
+            cb_max_slippage_bps: 10_000.0,
+            min_liquidity: 0.0,
+            cb_min_depth_usd: 0.0,
+            ..Default::default()
+        };
+        let risk = RiskManager::new(config);
+        let mut trade = Trade {
+            shares: 200.0,
+            ..fixtures::trade("market1")
+        };
+        risk.record_trade(&trade, &OrderResponse {
+            order_id: "o1".to_string(),
+            status: OrderStatus::Filled,
+            filled_shares: 200.0,
+            avg_fill_price: 0.5,
+            fees: 0.0,
+        });
+        let mut kill_signal = risk.kill_signal();
+        
+        // Nothing realized, but YES marked down to 0.2 is a $60 paper loss
+        let mut market = Market {
+            yes_price: 0.2,
+            no_price: 0.8,
+            ..fixtures::market("market1")
+        };
+        risk.mark_market(&market);
+        
+        let state = risk.get_state();
+        assert!((state.pnl_today + 60.0).abs() < 1e-9);
+        assert!(matches!(state.kill_reason, Some(KillReason::DailyLoss { .. })));
+        assert!(*kill_signal.borrow_and_update());
+        
+        // New entries are blocked, exits are not
+        assert!(risk.check_can_trade(&trade, &market, 10.0).is_err());
+        trade.side = TradeSide::SELL;
+        assert!(risk.check_can_trade(&trade, &market, 10.0).is_ok());
+        
+        // Recovery alone doesn't lift it; the next day does
+        market.yes_price = 0.5;
+        risk.mark_market(&market);
+        assert!(risk.get_state().kill_reason.is_some());
+        risk.reset_daily_stats();
+        assert!(risk.get_state().kill_reason.is_none());
+        assert!(!*kill_signal.borrow_and_update());
+    }
+    
+    #[test]
+    fn test_one_whale_cannot_use_the_whole_budget() {
+        let mut config = Config {
+            min_liquidity: 0.0,
+            cb_min_depth_usd: 0.0,
+            market_cooldown_secs: 0,
+            ..Default::default()
+        };
+        config.wallet_budgets.insert("0xbad".to_string(), WalletBudget {
+            bankroll: 100.0,
+            max_exposure: 60.0,
+            max_daily_volume: 80.0,
+        });
+        let risk = RiskManager::new(config);
+        let mut trade = Trade {
+            wallet: "0xBAD".to_string(),
+            ..fixtures::trade("market1")
+        };
+        let market = fixtures::market("market1");
+        risk.record_trade(&trade, &OrderResponse {
+            order_id: "o1".to_string(),
+            status: OrderStatus::Filled,
+            filled_shares: 100.0,
+            avg_fill_price: 0.5,
+            fees: 0.0,
+        });
+        
+        // $50 at risk of a $60 cap and $100 bankroll
+        assert!((risk.wallet_allocation("0xBAD", 5000.0) - 50.0).abs() < 1e-9);
+        assert!(risk.check_can_trade(&trade, &market, 20.0).is_err());
+        assert!(risk.check_can_trade(&trade, &market, 5.0).is_ok());
+        
+        // Exits still count toward its daily volume
+        trade.side = TradeSide::SELL;
+        assert!(risk.check_can_trade(&trade, &market, 35.0).is_err());
+        
+        // Other whales run on the default budget
+        trade.wallet = "0xgood".to_string();
+        trade.side = TradeSide::BUY;
+        assert!(risk.check_can_trade(&trade, &market, 50.0).is_ok());
+        assert!((risk.wallet_allocation("0xgood", 5000.0) - 1000.0).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn test_wallet_budgets_ignore_address_case() {
+        let mut config = Config {
+            min_liquidity: 0.0,
+            cb_min_depth_usd: 0.0,
+            market_cooldown_secs: 0,
+            ..Default::default()
+        };
+        config.wallet_budgets.insert("0xabc".to_string(), WalletBudget {
+            bankroll: 100.0,
+            max_exposure: 1000.0,
+            max_daily_volume: 80.0,
+        });
+        let risk = RiskManager::new(config);
+        let market = fixtures::market("market1");
+        let trade = Trade { wallet: "0xABC".to_string(), ..fixtures::trade("market1") };
+        risk.record_trade(&trade, &OrderResponse {
+            order_id: "o1".to_string(),
+            status: OrderStatus::Filled,
+            filled_shares: 100.0,
+            avg_fill_price: 0.5,
+            fees: 0.0,
+        });
+        
+        // The same whale reported in other casings shares one budget
+        let mixed = Trade { wallet: "0xAbC".to_string(), ..fixtures::trade("market1") };
+        let err = risk.check_can_trade(&mixed, &market, 40.0).unwrap_err();
+        assert!(err.to_string().contains("Daily volume limit for"), "{}", err);
+        assert!(risk.check_can_trade(&mixed, &market, 20.0).is_ok());
+        assert_eq!(risk.exposure().wallet_at_risk("0xabc"), 50.0);
+        assert!((risk.wallet_allocation("0xaBc", 5000.0) - 50.0).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn test_portfolio_concentration_limits() {
+        let config = Config {
+            min_liquidity: 0.0,
+            cb_min_depth_usd: 0.0,
+            market_cooldown_secs: 0,
+            max_open_positions: 2,
+            max_market_concentration: 0.2,
+            max_event_concentration: 0.3,
+            max_gross_exposure_pct: 0.3,
+            default_wallet_budget: WalletBudget { bankroll: 1e6, max_exposure: 1e6, max_daily_volume: 1e6 },
+            ..Default::default()
+        };
+        let risk = RiskManager::new(config);
+        risk.record_balance(1000.0);
+        
+        let buy = |event_id: &str, market_id: &str| Trade {
+            event_id: event_id.to_string(),
+            ..fixtures::trade(market_id)
+        };
+        let market = |event_id: &str, market_id: &str| Market {
+            event_id: event_id.to_string(),
+            ..fixtures::market(market_id)
+        };
+        let filled = |usd: f64| OrderResponse {
+            order_id: "o1".to_string(),
+            status: OrderStatus::Filled,
+            filled_shares: usd / 0.5,
+            avg_fill_price: 0.5,
+            fees: 0.0,
+        };
+        
+        // $150 in one election market; equity stays $1000
+        risk.record_trade(&buy("election", "alice"), &filled(150.0));
+        
+        // Another $200 in a sibling market is fine on its own but too much for the event
+        let err = risk.check_can_trade(&buy("election", "bob"), &market("election", "bob"), 200.0).unwrap_err();
+        assert!(err.to_string().contains("Event concentration"), "{}", err);
+        let err = risk.check_can_trade(&buy("election", "alice"), &market("election", "alice"), 60.0).unwrap_err();
+        assert!(err.to_string().contains("Market concentration"), "{}", err);
+        
+        risk.record_trade(&buy("sports", "final"), &filled(100.0));
+        let err = risk.check_can_trade(&buy("sports", "final"), &market("sports", "final"), 60.0).unwrap_err();
+        assert!(err.to_string().contains("Gross exposure"), "{}", err);
+        let err = risk.check_can_trade(&buy("crypto", "btc"), &market("crypto", "btc"), 10.0).unwrap_err();
+        assert!(err.to_string().contains("Open position limit"), "{}", err);
+    }
+    
+    #[test]
+    fn test_disabled_wallet_can_only_exit() {
+        let config = Config {
+            min_liquidity: 0.0,
+            cb_min_depth_usd: 0.0,
+            market_cooldown_secs: 0,
+            ..Default::default()
+        };
+        let risk = RiskManager::new(config);
+        let mut trade = Trade {
+            wallet: "0xWhale".to_string(),
+            ..fixtures::trade("market1")
+        };
+        let market = fixtures::market("market1");
+        
+        risk.disable_wallet("0xwhale", "ROI -40.0% over 12 positions is below -10.0%");
+        let err = risk.check_can_trade(&trade, &market, 10.0).unwrap_err();
+        assert!(err.to_string().contains("disabled"), "{}", err);
+        
+        trade.side = TradeSide::SELL;
+        assert!(risk.check_can_trade(&trade, &market, 10.0).is_ok());
+        
+        risk.enable_wallet("0xWHALE");
+        trade.side = TradeSide::BUY;
+        assert!(risk.check_can_trade(&trade, &market, 10.0).is_ok());
+    }
 }
@@ -0,0 +1,203 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration as ChronoDuration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+// Longest we sleep between checks. Timers don't advance while the host is
+// suspended, so re-reading the clock this often is what catches a missed reset.
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+// Rolls the daily risk limits over at a fixed wall-clock time in a given
+// timezone. Each trading day is named by the local date on which it started,
+// and the last one reset is persisted so a restart neither skips nor repeats it.
+pub struct DailyResetScheduler {
+    clock: Arc<dyn Clock>,
+    at: NaiveTime,
+    tz: Tz,
+    state_path: Option<PathBuf>,
+    last_reset: Option<NaiveDate>,
+}
+
+impl DailyResetScheduler {
+    pub fn new(clock: Arc<dyn Clock>, at: NaiveTime, tz: Tz, state_path: Option<PathBuf>) -> Self {
+        let last_reset = state_path.as_ref().and_then(|path| {
+            let raw = std::fs::read_to_string(path).ok()?;
+            match NaiveDate::parse_from_str(raw.trim(), "%Y-%m-%d") {
+                Ok(date) => Some(date),
+                Err(e) => {
+                    tracing::warn!("Ignoring unreadable reset state in {}: {}", path.display(), e);
+                    None
+                }
+            }
+        });
+
+        Self { clock, at, tz, state_path, last_reset }
+    }
+
+    pub fn last_reset(&self) -> Option<NaiveDate> {
+        self.last_reset
+    }
+
+    // The trading day `now` falls in
+    pub fn trading_day(&self, now: DateTime<Utc>) -> NaiveDate {
+        let local = now.with_timezone(&self.tz);
+        if local.time() >= self.at {
+            local.date_naive()
+        } else {
+            local.date_naive() - ChronoDuration::days(1)
+        }
+    }
+
+    // When the trading day after `day` starts
+    pub fn next_reset_after(&self, day: NaiveDate) -> DateTime<Utc> {
+        let next = (day + ChronoDuration::days(1)).and_time(self.at);
+        match self.tz.from_local_datetime(&next).earliest() {
+            Some(t) => t.with_timezone(&Utc),
+            // Reset time falls in a DST gap: go as soon as the clocks jump
+            None => self.tz.from_local_datetime(&(next + ChronoDuration::hours(1)))
+                .earliest()
+                .map(|t| t.with_timezone(&Utc))
+                .unwrap_or_else(|| Utc.from_utc_datetime(&next)),
+        }
+    }
+
+    // The trading day to reset into, if we haven't already. However many
+    // boundaries were missed, that's a single reset.
+    pub fn due(&self) -> Option<NaiveDate> {
+        let day = self.trading_day(self.clock.now());
+        match self.last_reset {
+            Some(last) if last >= day => None,
+            _ => Some(day),
+        }
+    }
+
+    pub fn mark_reset(&mut self, day: NaiveDate) -> Result<()> {
+        self.last_reset = Some(day);
+        if let Some(path) = &self.state_path {
+            if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(path, day.format("%Y-%m-%d").to_string())
+                .with_context(|| format!("Failed to persist reset date to {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    pub async fn run<F: FnMut()>(mut self, mut on_reset: F) {
+        loop {
+            if let Some(day) = self.due() {
+                if self.last_reset.is_some_and(|last| last + ChronoDuration::days(1) < day) {
+                    tracing::warn!("Missed daily reset(s) since {}, catching up", self.last_reset.unwrap());
+                }
+                on_reset();
+                if let Err(e) = self.mark_reset(day) {
+                    tracing::error!("{:#}", e);
+                }
+                tracing::info!("Daily reset done for trading day {} ({})", day, self.tz);
+            }
+
+            let now = self.clock.now();
+            let next = self.next_reset_after(self.trading_day(now));
+            let wait = (next - now).to_std().unwrap_or_default().min(MAX_SLEEP);
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct FakeClock(Mutex<DateTime<Utc>>);
+
+    impl FakeClock {
+        fn at(rfc3339: &str) -> Arc<Self> {
+            Arc::new(Self(Mutex::new(rfc3339.parse().unwrap())))
+        }
+
+        fn set(&self, rfc3339: &str) {
+            *self.0.lock().unwrap() = rfc3339.parse().unwrap();
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> DateTime<Utc> {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    fn new_york_5pm(clock: Arc<FakeClock>, state_path: Option<PathBuf>) -> DailyResetScheduler {
+        let at = NaiveTime::from_hms_opt(17, 0, 0).unwrap();
+        DailyResetScheduler::new(clock, at, chrono_tz::America::New_York, state_path)
+    }
+
+    #[test]
+    fn test_resets_once_per_local_day() {
+        // 16:30 in New York (EDT)
+        let clock = FakeClock::at("2024-06-03T20:30:00Z");
+        let mut scheduler = new_york_5pm(clock.clone(), None);
+        assert_eq!(scheduler.due(), Some(date("2024-06-02")));
+        scheduler.mark_reset(date("2024-06-02")).unwrap();
+        assert_eq!(scheduler.due(), None);
+
+        clock.set("2024-06-03T21:00:00Z");
+        assert_eq!(scheduler.due(), Some(date("2024-06-03")));
+        assert_eq!(scheduler.next_reset_after(date("2024-06-03")), "2024-06-04T21:00:00Z".parse::<DateTime<Utc>>().unwrap());
+    }
+
+    #[test]
+    fn test_catches_up_after_downtime_with_one_reset() {
+        let clock = FakeClock::at("2024-06-03T22:00:00Z");
+        let mut scheduler = new_york_5pm(clock.clone(), None);
+        scheduler.mark_reset(date("2024-06-03")).unwrap();
+
+        // Asleep for three days
+        clock.set("2024-06-06T23:00:00Z");
+        assert_eq!(scheduler.due(), Some(date("2024-06-06")));
+        scheduler.mark_reset(date("2024-06-06")).unwrap();
+        assert_eq!(scheduler.due(), None);
+    }
+
+    #[test]
+    fn test_follows_dst_change() {
+        let clock = FakeClock::at("2024-03-08T12:00:00Z");
+        let scheduler = new_york_5pm(clock, None);
+        // 17:00 EST before the switch, 17:00 EDT after it
+        assert_eq!(scheduler.next_reset_after(date("2024-03-07")), "2024-03-08T22:00:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert_eq!(scheduler.next_reset_after(date("2024-03-10")), "2024-03-11T21:00:00Z".parse::<DateTime<Utc>>().unwrap());
+    }
+
+    #[test]
+    fn test_last_reset_survives_restart() {
+        let path = std::env::temp_dir().join(format!("last_reset_{}", std::process::id()));
+        let clock = FakeClock::at("2024-06-03T22:00:00Z");
+
+        let mut scheduler = new_york_5pm(clock.clone(), Some(path.clone()));
+        scheduler.mark_reset(date("2024-06-03")).unwrap();
+
+        let restarted = new_york_5pm(clock, Some(path.clone()));
+        assert_eq!(restarted.last_reset(), Some(date("2024-06-03")));
+        assert_eq!(restarted.due(), None);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
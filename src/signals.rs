@@ -0,0 +1,131 @@
+use crate::types::{Config, Trade, TradeSide};
+use anyhow::{Result, bail};
+use std::collections::VecDeque;
+
+const SKEW_SAMPLES: usize = 32;
+
+// How far our clock runs ahead of the exchange's, from the Date header of API
+// responses. The header has whole-second resolution, so single samples are
+// noisy; the median over recent ones is what gets used.
+#[derive(Debug, Default)]
+pub struct ClockSkew {
+    samples: VecDeque<i64>,  // Local minus server, ms
+}
+
+impl ClockSkew {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn observe(&mut self, local_ms: i64, server_ms: i64) {
+        // The server truncated to the second; assume we're mid-second on average
+        self.samples.push_back(local_ms - (server_ms + 500));
+        if self.samples.len() > SKEW_SAMPLES {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn estimate_ms(&self) -> i64 {
+        if self.samples.is_empty() {
+            return 0;
+        }
+        let mut sorted: Vec<i64> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        sorted[sorted.len() / 2]
+    }
+}
+
+// Rejects whale trades that are too old to copy or whose price has moved away
+pub struct SignalGuard {
+    config: Config,
+}
+
+impl SignalGuard {
+    pub fn new(config: Config) -> Self {
+        Self { config }
+    }
+
+    // Age is measured on the exchange's clock: our time minus the skew
+    pub fn check_fresh(&self, trade: &Trade, now_ms: i64, skew_ms: i64) -> Result<()> {
+        let age_ms = now_ms - skew_ms - trade.timestamp * 1000;
+        if age_ms > self.config.max_signal_age_secs as i64 * 1000 {
+            bail!("Signal is {:.1}s old (max {}s, clock skew {}ms)",
+                age_ms as f64 / 1000.0, self.config.max_signal_age_secs, skew_ms);
+        }
+        Ok(())
+    }
+
+    // `best` is the price we'd get now: best ask for a buy, best bid for a
+    // sell. Only adverse drift counts.
+    pub fn check_drift(&self, trade: &Trade, best: Option<f64>) -> Result<()> {
+        let Some(best) = best else {
+            bail!("No {} liquidity on the book", match trade.side {
+                TradeSide::BUY => "ask",
+                TradeSide::SELL => "bid",
+            });
+        };
+        let drift = match trade.side {
+            TradeSide::BUY => best - trade.price,
+            TradeSide::SELL => trade.price - best,
+        };
+        if drift > self.config.max_price_drift {
+            bail!("Price drifted {:.3} against us since the whale's fill at {:.3} (now {:.3}, max {:.3})",
+                drift, trade.price, best, self.config.max_price_drift);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::fixtures;
+
+    fn trade(side: TradeSide) -> Trade {
+        Trade {
+            side,
+            timestamp: 1_700_000_000,
+            ..fixtures::trade("market1")
+        }
+    }
+
+    fn guard() -> SignalGuard {
+        SignalGuard::new(Config { max_signal_age_secs: 30, max_price_drift: 0.02, ..Default::default() })
+    }
+
+    #[test]
+    fn test_stale_signal_accounts_for_skew() {
+        let guard = guard();
+        let trade = trade(TradeSide::BUY);
+        let placed_ms = trade.timestamp * 1000;
+
+        assert!(guard.check_fresh(&trade, placed_ms + 10_000, 0).is_ok());
+        assert!(guard.check_fresh(&trade, placed_ms + 45_000, 0).is_err());
+
+        // Our clock is 20s fast, so the trade is really only 25s old
+        assert!(guard.check_fresh(&trade, placed_ms + 45_000, 20_000).is_ok());
+    }
+
+    #[test]
+    fn test_skew_estimate_is_median() {
+        let mut skew = ClockSkew::new();
+        assert_eq!(skew.estimate_ms(), 0);
+        for (local, server) in [(10_700, 10_000), (11_600, 11_000), (20_000, 12_000), (13_800, 13_000), (14_700, 14_000)] {
+            skew.observe(local, server);
+        }
+        // The 8s outlier (a slow response) doesn't move it
+        assert_eq!(skew.estimate_ms(), 200);
+    }
+
+    #[test]
+    fn test_only_adverse_drift_rejects() {
+        let guard = guard();
+        assert!(guard.check_drift(&trade(TradeSide::BUY), Some(0.51)).is_ok());
+        assert!(guard.check_drift(&trade(TradeSide::BUY), Some(0.53)).is_err());
+        assert!(guard.check_drift(&trade(TradeSide::BUY), Some(0.40)).is_ok());
+
+        assert!(guard.check_drift(&trade(TradeSide::SELL), Some(0.47)).is_err());
+        assert!(guard.check_drift(&trade(TradeSide::SELL), Some(0.60)).is_ok());
+        assert!(guard.check_drift(&trade(TradeSide::SELL), None).is_err());
+    }
+}
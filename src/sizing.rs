@@ -1,13 +1,25 @@
 use crate::types::{Config, SizingMode, Trade};
 use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::RwLock;
 
 pub struct PositionSizer {
     config: Config,
+    confidence: RwLock<HashMap<String, f64>>,  // Per-wallet multiplier from scoring, keyed lowercase
 }
 
 impl PositionSizer {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        Self { config, confidence: RwLock::new(HashMap::new()) }
+    }
+    
+    // Weights future copies of `wallet` by how well it has been performing
+    pub fn set_confidence(&self, wallet: &str, multiplier: f64) {
+        self.confidence.write().unwrap().insert(wallet.to_lowercase(), multiplier);
+    }
+    
+    pub fn confidence(&self, wallet: &str) -> f64 {
+        self.confidence.read().unwrap().get(&wallet.to_lowercase()).copied().unwrap_or(1.0)
     }
     
     pub async fn calculate_size(&self, whale_trade: &Trade, your_balance: f64, whale_balance: f64) -> Result<f64> {
@@ -25,6 +37,7 @@ impl PositionSizer {
                 whale_trade.shares * multiplier * self.config.proportional_ratio
             },
         };
+        let size = size * self.confidence(&whale_trade.wallet);
         
         // Apply limits
         let size = size.max(self.config.min_stake);
@@ -43,6 +56,17 @@ impl PositionSizer {
         Ok(size)
     }
     
+    // Scales a calculated size (e.g. by consensus agreement) without breaking
+    // the stake cap or the balance buffer
+    pub fn scale(&self, size: f64, multiplier: f64, your_balance: f64) -> f64 {
+        if multiplier == 1.0 {
+            return size;
+        }
+        (size * multiplier)
+            .min(self.config.max_stake)
+            .min(your_balance * 0.95)
+    }
+    
     fn get_tier_multiplier(&self, trade_size_usd: f64) -> f64 {
         // Tier-based multipliers
         // Small trades get lower weight, large trades get higher weight
@@ -68,7 +92,7 @@ impl PositionSizer {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::TradeSide;
+    use crate::api::mock::fixtures;
     
     #[tokio::test]
     async fn test_fixed_sizing() {
@@ -81,16 +105,7 @@ mod tests {
         };
         
         let sizer = PositionSizer::new(config);
-        let trade = Trade {
-            wallet: "0xwhale".to_string(),
-            event_id: "event1".to_string(),
-            market_id: "market1".to_string(),
-            side: TradeSide::BUY,
-            shares: 100.0,
-            price: 0.5,
-            timestamp: 0,
-            tx_hash: None,
-        };
+        let trade = fixtures::trade("market1");
         
         let size = sizer.calculate_size(&trade, 1000.0, 10000.0).await.unwrap();
         assert_eq!(size, 25.0);
@@ -106,20 +121,32 @@ mod tests {
         };
         
         let sizer = PositionSizer::new(config);
-        let trade = Trade {
-            wallet: "0xwhale".to_string(),
-            event_id: "event1".to_string(),
-            market_id: "market1".to_string(),
-            side: TradeSide::BUY,
-            shares: 100.0,
-            price: 0.5,
-            timestamp: 0,
-            tx_hash: None,
-        };
+        let trade = fixtures::trade("market1");
         
         // Your balance is 10% of whale's balance
         // So you should trade 10% of whale's trade
         let size = sizer.calculate_size(&trade, 1000.0, 10000.0).await.unwrap();
         assert_eq!(size, 5.0); // 100 shares * 0.5 price * 0.1 ratio = 5
     }
+    
+    #[tokio::test]
+    async fn test_confidence_scales_size() {
+        let config = Config {
+            sizing_mode: SizingMode::Fixed,
+            fixed_stake: 25.0,
+            min_stake: 5.0,
+            max_stake: 100.0,
+            ..Default::default()
+        };
+        
+        let sizer = PositionSizer::new(config);
+        let trade = Trade {
+            wallet: "0xWhale".to_string(),
+            ..fixtures::trade("market1")
+        };
+        
+        sizer.set_confidence("0xwhale", 0.5);
+        let size = sizer.calculate_size(&trade, 1000.0, 10000.0).await.unwrap();
+        assert_eq!(size, 12.5);
+    }
 }
\ No newline at end of file
@@ -1,4 +1,7 @@
+use crate::breaker::{BreakerPhase, TripReason};
+use crate::killswitch::KillReason;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 impl Default for Config {
     fn default() -> Self {
@@ -8,19 +11,80 @@ impl Default for Config {
             private_key: String::new(),
             polymarket_api: String::new(),
             ws_url: String::new(),
+            market_ws_url: String::new(),
             rpc_url: String::new(),
+            order_book_mirror: true,
+            book_idle_timeout_secs: 60,
+            api_timeout_ms: 10000,
+            api_connect_timeout_ms: 3000,
+            http_proxy: String::new(),
+            api_prewarm_connections: 4,
+            rate_limit_headroom: 0.8,
             sizing_mode: SizingMode::Fixed,
             fixed_stake: 25.0,
             proportional_ratio: 0.02,
             min_stake: 5.0,
             max_stake: 100.0,
             max_exposure_per_event: 500.0,
+            max_open_positions: 20,
+            max_market_concentration: 0.2,
+            max_event_concentration: 0.35,
+            max_gross_exposure_pct: 0.8,
             max_daily_volume: 2000.0,
+            max_hourly_volume: 500.0,
+            max_trades_per_minute: 10,
+            max_wallet_trades_per_minute: 5,
+            market_cooldown_secs: 30,
+            default_wallet_budget: WalletBudget::default(),
+            wallet_budgets: HashMap::new(),
+            filter_rules: FilterRules::default(),
+            wallet_filter_rules: HashMap::new(),
             min_liquidity: 1000.0,
             cb_consecutive_trigger: 3,
             cb_min_depth_usd: 100.0,
+            cb_cooldown_secs: 300,
+            cb_probe_trades: 1,
+            cb_error_window_secs: 300,
+            cb_error_rate_threshold: 0.5,
+            cb_error_rate_min_samples: 10,
+            cb_max_daily_loss: 200.0,
+            cb_max_drawdown_pct: 0.25,
+            cb_latency_spike_ms: 10_000,
+            cb_max_slippage_bps: 500.0,
+            max_daily_loss: 300.0,
+            max_drawdown_pct: 0.3,
+            flatten_on_kill: false,
+            mark_interval_secs: 60,
+            daily_reset_time: "00:00".to_string(),
+            daily_reset_timezone: "UTC".to_string(),
+            reset_state_path: String::new(),
+            consensus_enabled: false,
+            consensus_threshold: 2.0,
+            consensus_window_secs: 300,
+            consensus_max_multiplier: 2.0,
+            wallet_weights: HashMap::new(),
+            inverted_wallets: vec![],
+            max_signal_age_secs: 30,
+            max_price_drift: 0.02,
+            fill_aggregation_ms: 500,
+            score_sizing: false,
+            auto_disable_wallets: false,
+            score_interval_secs: 3600,
+            score_lookback_days: 30,
+            min_scored_positions: 10,
+            min_wallet_roi: -0.1,
+            market_cache_ttl_ms: 2000,
+            event_cache_ttl_secs: 300,
+            balance_cache_ttl_secs: 60,
+            cache_capacity: 1000,
+            balance_reconcile_secs: 30,
+            pipeline_workers: 4,
+            signal_queue_capacity: 1000,
+            overflow_policy: OverflowPolicy::DropStale,
             retry_attempts: 4,
             retry_delay_ms: 500,
+            order_ttl_secs: 300,
+            order_poll_interval_ms: 2000,
         }
     }
 }
@@ -30,28 +94,110 @@ pub struct Trade {
     pub wallet: String,
     pub event_id: String,
     pub market_id: String,
+    pub outcome: Outcome,
+    pub token_id: Option<String>,  // CLOB asset id, when the feed reports it
     pub side: TradeSide,
     pub shares: f64,
-    pub price: f64,
+    pub price: f64,  // Price of `outcome`, not always the YES price
     pub timestamp: i64,
     pub tx_hash: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+impl Trade {
+    // The same trade on the other outcome at the complementary price: a whale
+    // buying YES at 0.30 becomes a buy of NO at 0.70. The feed's token id is
+    // the whale's side, so it's dropped and routing resolves ours from the market.
+    pub fn inverted(&self) -> Trade {
+        Trade {
+            outcome: self.outcome.opposite(),
+            token_id: None,
+            price: 1.0 - self.price,
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum TradeSide {
     BUY,
     SELL,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Outcome {
+    Yes,
+    No,
+}
+
+impl Outcome {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_uppercase().as_str() {
+            "YES" => Some(Outcome::Yes),
+            "NO" => Some(Outcome::No),
+            _ => None,
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Outcome::Yes => Outcome::No,
+            Outcome::No => Outcome::Yes,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Market {
     pub id: String,
     pub event_id: String,
     pub question: String,
+    pub yes_token_id: String,
+    pub no_token_id: String,
     pub yes_price: f64,
     pub no_price: f64,
     pub liquidity: f64,
     pub volume_24h: f64,
+    pub neg_risk: bool,  // Settles on the neg-risk exchange
+    pub end_date: Option<i64>,  // Expected resolution, unix seconds
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+    pub resolved: Option<Outcome>,  // Winning outcome once the market has resolved
+}
+
+// A group of markets under one event_id. In a neg-risk event the markets are
+// mutually exclusive: at most one of them resolves YES.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub id: String,
+    pub neg_risk: bool,
+    pub markets: Vec<Market>,
+}
+
+impl Market {
+    pub fn token_id(&self, outcome: Outcome) -> &str {
+        match outcome {
+            Outcome::Yes => &self.yes_token_id,
+            Outcome::No => &self.no_token_id,
+        }
+    }
+
+    pub fn price(&self, outcome: Outcome) -> f64 {
+        match outcome {
+            Outcome::Yes => self.yes_price,
+            Outcome::No => self.no_price,
+        }
+    }
+
+    pub fn outcome_for_token(&self, token_id: &str) -> Option<Outcome> {
+        if token_id == self.yes_token_id {
+            Some(Outcome::Yes)
+        } else if token_id == self.no_token_id {
+            Some(Outcome::No)
+        } else {
+            None
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,11 +213,16 @@ pub struct Position {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderRequest {
+    pub client_order_id: String,
     pub market_id: String,
+    pub token_id: String,
+    pub outcome: Outcome,
     pub side: TradeSide,
     pub shares: f64,
     pub price: Option<f64>,
     pub order_type: OrderType,
+    pub expiration: Option<i64>,  // Unix seconds, GTD only
+    pub neg_risk: bool,  // Route to the neg-risk exchange
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -82,12 +233,68 @@ pub enum OrderType {
     GTD,  // Good-Till-Date
 }
 
+impl OrderType {
+    // Limit-style orders may legitimately sit on the book after placement
+    pub fn rests_on_book(&self) -> bool {
+        matches!(self, OrderType::LIMIT | OrderType::GTD)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderStatus {
+    Pending,
+    Open,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    Rejected,
+    Expired,
+    #[serde(other)]
+    Unknown,
+}
+
+impl OrderStatus {
+    pub fn parse(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "pending" | "delayed" => OrderStatus::Pending,
+            "open" | "live" => OrderStatus::Open,
+            "partially_filled" => OrderStatus::PartiallyFilled,
+            "filled" | "matched" => OrderStatus::Filled,
+            "cancelled" | "canceled" => OrderStatus::Cancelled,
+            "rejected" => OrderStatus::Rejected,
+            "expired" => OrderStatus::Expired,
+            _ => OrderStatus::Unknown,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderStatus::Pending => "pending",
+            OrderStatus::Open => "open",
+            OrderStatus::PartiallyFilled => "partially_filled",
+            OrderStatus::Filled => "filled",
+            OrderStatus::Cancelled => "cancelled",
+            OrderStatus::Rejected => "rejected",
+            OrderStatus::Expired => "expired",
+            OrderStatus::Unknown => "unknown",
+        }
+    }
+}
+
+impl std::fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderResponse {
     pub order_id: String,
-    pub status: String,
+    pub status: OrderStatus,
     pub filled_shares: f64,
     pub avg_fill_price: f64,
+    pub fees: f64,  // USD
 }
 
 #[derive(Debug, Clone)]
@@ -95,8 +302,56 @@ pub struct CircuitBreakerState {
     pub consecutive_errors: u32,
     pub total_trades_today: u32,
     pub total_volume_today: f64,
+    pub realized_pnl_today: f64,
+    pub phase: BreakerPhase,
     pub is_tripped: bool,
-    pub trip_reason: Option<String>,
+    pub trip_reason: Option<TripReason>,
+    pub pnl_today: f64,  // Realized + unrealized
+    pub kill_reason: Option<KillReason>,
+}
+
+// Limits applied to the copies of one tracked wallet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WalletBudget {
+    pub bankroll: f64,  // Share of our balance this whale may size against
+    pub max_exposure: f64,
+    pub max_daily_volume: f64,
+}
+
+impl Default for WalletBudget {
+    fn default() -> Self {
+        Self {
+            bankroll: 1000.0,
+            max_exposure: 250.0,
+            max_daily_volume: 1000.0,
+        }
+    }
+}
+
+// Which markets we're willing to copy into
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilterRules {
+    pub min_price: f64,
+    pub max_price: f64,
+    pub min_hours_to_resolution: f64,
+    pub max_days_to_resolution: f64,
+    pub blocked_categories: Vec<String>,  // Checked against category and tags
+    pub deny_keywords: Option<String>,  // Regex over the question
+    pub allow_keywords: Option<String>,  // Regex the question must match
+}
+
+impl Default for FilterRules {
+    fn default() -> Self {
+        Self {
+            min_price: 0.05,
+            max_price: 0.95,
+            min_hours_to_resolution: 1.0,
+            max_days_to_resolution: 365.0,
+            blocked_categories: vec![],
+            deny_keywords: None,
+            allow_keywords: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -106,8 +361,19 @@ pub struct Config {
     pub private_key: String,
     pub polymarket_api: String,
     pub ws_url: String,
+    pub market_ws_url: String,  // CLOB market channel, for the order book mirror
     pub rpc_url: String,
     
+    pub order_book_mirror: bool,  // Local books from the market channel instead of REST polling
+    pub book_idle_timeout_secs: u64,  // A feed this quiet is presumed dead and its books dropped
+    
+    // HTTP client
+    pub api_timeout_ms: u64,
+    pub api_connect_timeout_ms: u64,
+    pub http_proxy: String,  // Empty: HTTP(S)_PROXY from the environment, if set
+    pub api_prewarm_connections: usize,
+    pub rate_limit_headroom: f64,  // Fraction of the exchange's published limits to use
+    
     // Sizing
     pub sizing_mode: SizingMode,
     pub fixed_stake: f64,
@@ -117,14 +383,91 @@ pub struct Config {
     
     // Risk
     pub max_exposure_per_event: f64,
+    pub max_open_positions: usize,
+    pub max_market_concentration: f64,  // Fraction of equity at risk in one market
+    pub max_event_concentration: f64,  // Fraction of equity at risk in one event
+    pub max_gross_exposure_pct: f64,  // Marked value of all positions over equity
     pub max_daily_volume: f64,
+    pub max_hourly_volume: f64,
+    pub max_trades_per_minute: u32,
+    pub max_wallet_trades_per_minute: u32,
+    pub market_cooldown_secs: u64,  // Per market and side, after a copy
+    pub default_wallet_budget: WalletBudget,
+    pub wallet_budgets: HashMap<String, WalletBudget>,  // Keyed by lowercase address
+    
+    // Market filters
+    pub filter_rules: FilterRules,
+    pub wallet_filter_rules: HashMap<String, FilterRules>,  // Keyed by lowercase address
     pub min_liquidity: f64,
     pub cb_consecutive_trigger: u32,
     pub cb_min_depth_usd: f64,
+    pub cb_cooldown_secs: u64,
+    pub cb_probe_trades: u32,
+    pub cb_error_window_secs: u64,
+    pub cb_error_rate_threshold: f64,  // Fraction of failed attempts in the window
+    pub cb_error_rate_min_samples: u32,
+    pub cb_max_daily_loss: f64,  // USD, realized
+    pub cb_max_drawdown_pct: f64,  // Fraction of peak equity
+    pub cb_latency_spike_ms: u64,
+    pub cb_max_slippage_bps: f64,
+    pub max_daily_loss: f64,  // USD, realized + unrealized
+    pub max_drawdown_pct: f64,  // Fraction of peak marked equity
+    pub flatten_on_kill: bool,
+    pub mark_interval_secs: u64,
+    pub daily_reset_time: String,  // HH:MM, local to daily_reset_timezone
+    pub daily_reset_timezone: String,  // IANA name, e.g. America/New_York
+    pub reset_state_path: String,  // Empty disables persistence
+    
+    // Consensus mode
+    pub consensus_enabled: bool,
+    pub consensus_threshold: f64,  // Summed wallet weight needed to copy
+    pub consensus_window_secs: u64,
+    pub consensus_max_multiplier: f64,
+    pub wallet_weights: HashMap<String, f64>,  // Keyed by lowercase address, default 1.0
+    pub inverted_wallets: Vec<String>,  // Lowercase; faded rather than copied
+    
+    // Signal freshness
+    pub max_signal_age_secs: u64,
+    pub max_price_drift: f64,  // Adverse move from the whale's fill, in price units
+    pub fill_aggregation_ms: u64,  // Window to coalesce a whale's fills in; 0 disables
+    
+    // Whale scoring
+    pub score_sizing: bool,  // Scale copies by each wallet's recent ROI
+    pub auto_disable_wallets: bool,
+    pub score_interval_secs: u64,
+    pub score_lookback_days: u64,
+    pub min_scored_positions: usize,  // Closed positions before a score counts
+    pub min_wallet_roi: f64,  // Below this a wallet's buys stop being copied
+    
+    // API caching
+    pub market_cache_ttl_ms: u64,
+    pub event_cache_ttl_secs: u64,
+    pub balance_cache_ttl_secs: u64,  // Whale balances; ours is tracked from fills
+    pub cache_capacity: usize,  // Per resource, least recently used evicted first
+    pub balance_reconcile_secs: u64,
     
     // Execution
+    pub pipeline_workers: usize,  // Trades processed at once; one market is never parallel
+    pub signal_queue_capacity: usize,
+    pub overflow_policy: OverflowPolicy,
     pub retry_attempts: u32,
     pub retry_delay_ms: u64,
+    pub order_ttl_secs: u64,
+    pub order_poll_interval_ms: u64,
+}
+
+impl Config {
+    pub fn wallet_budget(&self, wallet: &str) -> &WalletBudget {
+        self.wallet_budgets.get(&wallet.to_lowercase()).unwrap_or(&self.default_wallet_budget)
+    }
+
+    pub fn is_inverted(&self, wallet: &str) -> bool {
+        self.inverted_wallets.contains(&wallet.to_lowercase())
+    }
+
+    pub fn wallet_weight(&self, wallet: &str) -> f64 {
+        self.wallet_weights.get(&wallet.to_lowercase()).copied().unwrap_or(1.0)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -134,6 +477,14 @@ pub enum SizingMode {
     TierBased,
 }
 
+// What happens to a new signal when the queue in front of the pipeline is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OverflowPolicy {
+    Block,  // Wait for room, stalling the watchers
+    DropOldest,
+    DropStale,  // Shed signals past max_signal_age_secs, else the oldest
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebSocketEvent {
     pub event_type: String,
@@ -1,4 +1,4 @@
-use crate::types::{Trade, TradeSide};
+use crate::types::{Outcome, Trade, TradeSide};
 use anyhow::{Context, Result};
 use async_channel::{Sender, Receiver, bounded};
 use futures_util::{SinkExt, StreamExt};
@@ -129,6 +129,8 @@ fn parse_trade_event(event: &serde_json::Value, wallet: &str) -> Option<Trade> {
         wallet: wallet.to_string(),
         event_id: data["event_id"].as_str()?.to_string(),
         market_id: data["market_id"].as_str()?.to_string(),
+        outcome: Outcome::parse(data["outcome"].as_str()?)?,
+        token_id: data["asset_id"].as_str().map(|s| s.to_string()),
         side: match data["side"].as_str()? {
             "BUY" => TradeSide::BUY,
             "SELL" => TradeSide::SELL,
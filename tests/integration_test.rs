@@ -140,12 +140,28 @@ mod tests {
 mod api_tests {
     #[tokio::test]
     async fn test_api_client_creation() {
-        let api_url = "https://api.polymarket.com".to_string();
-        let _api = polymarket_copy_bot::api::PolymarketApi::new(api_url);
+        let config = polymarket_copy_bot::types::Config {
+            polymarket_api: "https://api.polymarket.com".to_string(),
+            ..Default::default()
+        };
+        let _api = polymarket_copy_bot::api::PolymarketApi::new(&config).unwrap();
         
         // Test that we can create the client without panicking
         // If we reach this point, the client was created successfully
     }
+    
+    #[tokio::test]
+    async fn test_api_client_with_proxy() {
+        let mut config = polymarket_copy_bot::types::Config {
+            polymarket_api: "https://api.polymarket.com".to_string(),
+            http_proxy: "http://127.0.0.1:8080".to_string(),
+            ..Default::default()
+        };
+        assert!(polymarket_copy_bot::api::PolymarketApi::new(&config).is_ok());
+        
+        config.http_proxy = "not a proxy".to_string();
+        assert!(polymarket_copy_bot::api::PolymarketApi::new(&config).is_err());
+    }
 }
 
 // Circuit breaker tests
@@ -175,6 +191,7 @@ mod circuit_breaker_tests {
             cb_min_depth_usd: 100.0,
             retry_attempts: 4,
             retry_delay_ms: 500,
+            ..Default::default()
         };
         
         let risk = RiskManager::new(config);